@@ -0,0 +1,81 @@
+/*
+    Binary to check whether given grids are fish-friendly
+    (each file holds one grid in the text format: B = blue, . = water)
+*/
+
+use fish_friendly::render::{ImageFormat, RenderOptions};
+use fish_friendly::Grid;
+
+use clap::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(required = true, help = "Files with one grid each")]
+    files: Vec<PathBuf>,
+    #[arg(long, help = "Directory to save an image of each grid")]
+    images: Option<PathBuf>,
+    #[arg(long, help = "Image format (svg or ppm)", default_value = "svg")]
+    format: ImageFormat,
+    #[arg(long, help = "Cell size in images, in pixels", default_value = "20")]
+    cell_size: usize,
+    #[arg(long, help = "Color each connected component differently")]
+    components: bool,
+}
+
+fn load(file: &Path) -> Result<Grid, String> {
+    let text = fs::read_to_string(file).map_err(|err| err.to_string())?;
+    text.parse()
+}
+
+pub fn main() {
+    let args = Args::parse();
+
+    if let Some(dir) = &args.images {
+        fs::create_dir_all(dir).unwrap();
+    }
+    let mut options =
+        RenderOptions::new(args.cell_size).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+    options.color_components = args.components;
+
+    let mut failed = false;
+    for file in &args.files {
+        let grid = match load(file) {
+            Ok(grid) => grid,
+            Err(err) => {
+                eprintln!("Could not load {}: {}", file.display(), err);
+                failed = true;
+                continue;
+            }
+        };
+        match grid.fish_path() {
+            Some(path) => println!(
+                "{}: friendly (path of {} cells)",
+                file.display(),
+                path.len()
+            ),
+            None => println!("{}: not friendly", file.display()),
+        }
+        if let Some(dir) = &args.images {
+            let stem = file.file_stem().unwrap_or(file.as_os_str());
+            let name = format!(
+                "{}.{}",
+                stem.to_string_lossy(),
+                args.format.extension()
+            );
+            let image = dir.join(name);
+            if let Err(err) = grid.save_image(&image, args.format, &options) {
+                eprintln!("Could not save {}: {}", image.display(), err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
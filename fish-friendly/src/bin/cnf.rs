@@ -0,0 +1,65 @@
+/*
+    Binary to export the fish-friendly property as a DIMACS CNF formula
+*/
+
+use fish_friendly::cnf;
+use fish_friendly::count_friendly_grids;
+
+use clap::Parser;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(help = "Number of rows")]
+    rows: usize,
+    #[arg(help = "Number of columns")]
+    cols: usize,
+    #[arg(short, long, help = "Encode unfriendly grids instead")]
+    unfriendly: bool,
+    #[arg(short, long, help = "Output file (default: stdout)")]
+    output: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        help = "Check the built-in model count against the exhaustive count"
+    )]
+    check: bool,
+}
+
+pub fn main() {
+    let args = Args::parse();
+    if args.rows == 0 || args.cols == 0 {
+        eprintln!("The grid should have at least one row and one column");
+        std::process::exit(1);
+    }
+
+    let formula = cnf::encode(args.rows, args.cols, !args.unfriendly);
+    match &args.output {
+        Some(path) => {
+            let file = File::create(path).unwrap();
+            formula.write_dimacs(&mut BufWriter::new(file)).unwrap();
+        }
+        None => formula.write_dimacs(&mut io::stdout().lock()).unwrap(),
+    }
+
+    if args.check {
+        let models = formula.count_models();
+        let (friendly, total) = count_friendly_grids(args.rows, args.cols);
+        let expected =
+            if args.unfriendly { total - friendly } else { friendly };
+        eprintln!(
+            "{} variables, {} clauses: {} models (expected {})",
+            formula.get_num_vars(),
+            formula.get_num_clauses(),
+            models,
+            expected
+        );
+        if models != expected {
+            eprintln!("Model count does not match!");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+/*
+    Binary to count the fish-friendly completions of a partially colored grid
+    (read from a file, or from stdin if no file is given)
+*/
+
+use fish_friendly::partial::PartialGrid;
+
+use clap::Parser;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(help = "File with one row per line: B (blue), . (water), ? (free)")]
+    file: Option<PathBuf>,
+    #[arg(short, long, help = "Enumerate all completions instead of using DP")]
+    brute: bool,
+}
+
+pub fn main() {
+    let args = Args::parse();
+
+    let text = match &args.file {
+        Some(path) => fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("Could not read {}: {}", path.display(), err);
+            std::process::exit(1);
+        }),
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).unwrap();
+            text
+        }
+    };
+    let grid: PartialGrid = text.parse().unwrap_or_else(|err| {
+        eprintln!("Could not parse grid: {}", err);
+        std::process::exit(1);
+    });
+
+    let (friendly, total) = if args.brute {
+        grid.count_completions_brute()
+    } else {
+        grid.count_completions()
+    };
+
+    println!(
+        "=== Results for {} x {} grid with {} free cells ===",
+        grid.get_rows(),
+        grid.get_cols(),
+        grid.num_free()
+    );
+    print!("{}", grid);
+    println!(
+        "The fish can swim across in {} of {} completions ({:.3}%).",
+        friendly,
+        total,
+        (friendly as f64) * 100.0 / (total as f64),
+    );
+}
@@ -0,0 +1,49 @@
+/*
+    Binary to count grids whose blue cells form a single connected component,
+    and how many of those are fish-friendly
+*/
+
+use fish_friendly::connected::{
+    count_connected_dp, count_connected_grids_with,
+};
+use fish_friendly::progress::ProgressBar;
+
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(help = "Number of rows")]
+    rows: usize,
+    #[arg(help = "Number of columns")]
+    cols: usize,
+    #[arg(short, long, help = "Enumerate all grids instead of using DP")]
+    brute: bool,
+}
+
+pub fn main() {
+    let args = Args::parse();
+
+    let result = if args.brute {
+        count_connected_grids_with(
+            args.rows,
+            args.cols,
+            &mut ProgressBar::new(),
+        )
+    } else {
+        count_connected_dp(args.rows, args.cols)
+    };
+
+    println!("=== Results for {} x {} grids ===", args.rows, args.cols);
+    println!(
+        "The blue cells are connected in {} of {} cases ({:.3}%).",
+        result.connected,
+        result.total,
+        (result.connected as f64) * 100.0 / (result.total as f64),
+    );
+    println!(
+        "Of those, the fish can swim across in {} cases ({:.3}%).",
+        result.friendly,
+        (result.friendly as f64) * 100.0 / (result.connected as f64),
+    );
+}
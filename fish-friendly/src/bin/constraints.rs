@@ -0,0 +1,101 @@
+/*
+    Binary to count friendly grids under path constraints
+    (waypoints, no left turns, maximum path length)
+
+    Tabulates the count with no constraints, with each constraint on its own,
+    and with all of them together.
+*/
+
+use fish_friendly::constraints::{count_constrained_grids_with, Constraints};
+use fish_friendly::progress::ProgressBar;
+
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(help = "Number of rows")]
+    rows: usize,
+    #[arg(help = "Number of columns")]
+    cols: usize,
+    #[arg(
+        short,
+        long,
+        help = "Cell the path must pass through, as row,col (from 1); \
+                may be repeated",
+        value_parser = parse_cell
+    )]
+    waypoint: Vec<(usize, usize)>,
+    #[arg(short, long, help = "Forbid left turns")]
+    no_left_turns: bool,
+    #[arg(short, long, help = "Maximum number of cells on the path")]
+    max_length: Option<usize>,
+}
+
+fn parse_cell(s: &str) -> Result<(usize, usize), String> {
+    let (i, j) = s
+        .split_once(',')
+        .ok_or_else(|| format!("Expected row,col, got {:?}", s))?;
+    let parse = |x: &str| {
+        x.trim().parse::<usize>().map_err(|_| format!("Bad index {:?}", x))
+    };
+    Ok((parse(i)?, parse(j)?))
+}
+
+pub fn main() {
+    let args = Args::parse();
+    for &(i, j) in &args.waypoint {
+        if !(1..=args.rows).contains(&i) || !(1..=args.cols).contains(&j) {
+            eprintln!("Waypoint {},{} is outside the grid", i, j);
+            std::process::exit(1);
+        }
+    }
+
+    let mut labels = vec!["none".to_string()];
+    let mut constraints = vec![Constraints::default()];
+    for &(i, j) in &args.waypoint {
+        labels.push(format!("waypoint {},{}", i, j));
+        constraints.push(Constraints {
+            waypoints: vec![(i, j)],
+            ..Default::default()
+        });
+    }
+    if args.no_left_turns {
+        labels.push("no left turns".to_string());
+        constraints
+            .push(Constraints { no_left_turns: true, ..Default::default() });
+    }
+    if let Some(max_length) = args.max_length {
+        labels.push(format!("max length {}", max_length));
+        constraints.push(Constraints {
+            max_length: Some(max_length),
+            ..Default::default()
+        });
+    }
+    if constraints.len() > 2 {
+        labels.push("all".to_string());
+        constraints.push(Constraints {
+            waypoints: args.waypoint.clone(),
+            no_left_turns: args.no_left_turns,
+            max_length: args.max_length,
+        });
+    }
+
+    let (friendly, total) = count_constrained_grids_with(
+        args.rows,
+        args.cols,
+        &constraints,
+        &mut ProgressBar::new(),
+    );
+
+    println!("=== Results for {} x {} grids ===", args.rows, args.cols);
+    for (label, &count) in labels.iter().zip(&friendly) {
+        println!(
+            "{:>20}: {} of {} ({:.3}%)",
+            label,
+            count,
+            total,
+            (count as f64) * 100.0 / (total as f64),
+        );
+    }
+}
@@ -2,9 +2,12 @@
     Binary to count total # of grids
 */
 
-use fish_friendly::count_friendly_grids;
+use fish_friendly::progress::{LogProgress, ProgressBar};
+use fish_friendly::report::{CountMethod, CountResult};
 
 use clap::Parser;
+use std::fs::File;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -13,18 +16,60 @@ struct Args {
     rows: usize,
     #[arg(help = "Number of columns")]
     cols: usize,
+    #[arg(
+        short,
+        long,
+        help = "Log progress to a file instead of the terminal"
+    )]
+    log: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        help = "Counting method (brute-force or transfer-matrix)",
+        default_value = "brute-force"
+    )]
+    method: CountMethod,
+    #[arg(short, long, help = "Print the result as JSON")]
+    json: bool,
 }
 
 pub fn main() {
     let args = Args::parse();
 
-    let (friendly, total) = count_friendly_grids(args.rows, args.cols);
+    let result = match &args.log {
+        Some(path) => {
+            let file = File::create(path).unwrap_or_else(|err| {
+                eprintln!("Could not create {}: {}", path.display(), err);
+                std::process::exit(1);
+            });
+            let mut observer = LogProgress::new(file, 100);
+            let result = CountResult::count(
+                args.rows,
+                args.cols,
+                args.method,
+                &mut observer,
+            );
+            // The count itself is fine, so only warn
+            if let Some(err) = observer.error() {
+                eprintln!("Could not write to {}: {}", path.display(), err);
+            }
+            result
+        }
+        None => CountResult::count(
+            args.rows,
+            args.cols,
+            args.method,
+            &mut ProgressBar::new(),
+        ),
+    }
+    .unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
 
-    println!("=== Results for {} x {} grids ===", args.rows, args.cols);
-    println!(
-        "The fish can swim across in {} of {} cases ({:.3}%).",
-        friendly,
-        total,
-        (friendly as f64) * 100.0 / (total as f64),
-    );
+    if args.json {
+        println!("{}", result.to_json());
+    } else {
+        println!("{}", result);
+    }
 }
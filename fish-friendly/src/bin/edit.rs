@@ -0,0 +1,333 @@
+/*
+    Binary to interactively edit a grid in the terminal
+
+    Shows live whether the fish can swim across, along with its path,
+    or else a chain of water cells blocking it.
+    Grids are saved and loaded in the text format (B = blue, . = water).
+*/
+
+use fish_friendly::Grid;
+
+use clap::Parser;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{
+    Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{
+    self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::panic;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(help = "File to load from (if it exists) and save to")]
+    file: PathBuf,
+    #[arg(
+        short,
+        long,
+        help = "Number of rows (for a new grid)",
+        default_value = "5"
+    )]
+    rows: usize,
+    #[arg(
+        short,
+        long,
+        help = "Number of columns (for a new grid)",
+        default_value = "5"
+    )]
+    cols: usize,
+}
+
+struct Editor {
+    grid: Grid,
+    file: PathBuf,
+    // Cursor position (indexed like the grid, starting from 1)
+    row: usize,
+    col: usize,
+    message: String,
+}
+
+impl Editor {
+    fn draw<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let path = self.grid.fish_path();
+        let cut = self.grid.blocking_cut();
+        let highlighted: HashSet<(usize, usize)> =
+            path.iter().chain(cut.iter()).flatten().cloned().collect();
+
+        queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+        queue!(out, Print(format!("Editing {}", self.file.display())))?;
+        for i in 1..=self.grid.get_rows() {
+            queue!(out, MoveTo(0, (i + 1) as u16))?;
+            for j in 1..=self.grid.get_cols() {
+                let background = if self.grid.cell(i, j) {
+                    Color::DarkBlue
+                } else {
+                    Color::Grey
+                };
+                let marker = if !highlighted.contains(&(i, j)) {
+                    "  "
+                } else if path.is_some() {
+                    "~~"
+                } else {
+                    "XX"
+                };
+                let (left, right) = if (i, j) == (self.row, self.col) {
+                    ("[", "]")
+                } else {
+                    (" ", " ")
+                };
+                queue!(
+                    out,
+                    SetBackgroundColor(background),
+                    SetForegroundColor(if path.is_some() {
+                        Color::Yellow
+                    } else {
+                        Color::Red
+                    }),
+                    Print(format!("{}{}{}", left, marker, right)),
+                    ResetColor,
+                )?;
+            }
+        }
+
+        let status = match (&path, &cut) {
+            (Some(path), _) => {
+                format!(
+                    "The fish can swim across (path of {} cells: ~~)",
+                    path.len()
+                )
+            }
+            (None, Some(cut)) => {
+                format!(
+                    "The fish is blocked (chain of {} water cells: XX)",
+                    cut.len()
+                )
+            }
+            // Without backwards steps, the fish can be blocked without a
+            // chain of water cells (see Grid::blocking_cut)
+            (None, None) => "The fish is blocked".to_string(),
+        };
+        let bottom = (self.grid.get_rows() + 3) as u16;
+        queue!(out, MoveTo(0, bottom), Print(status))?;
+        queue!(
+            out,
+            MoveTo(0, bottom + 1),
+            Print("Arrows: move, space: toggle, s: save, r: reload, q: quit"),
+            MoveTo(0, bottom + 2),
+            Print(&self.message),
+        )?;
+        out.flush()
+    }
+
+    // Returns false when the editor should quit
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        let (rows, cols) = (self.grid.get_rows(), self.grid.get_cols());
+        self.message.clear();
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.row = (self.row - 1).max(1)
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.row = (self.row + 1).min(rows)
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.col = (self.col - 1).max(1)
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.col = (self.col + 1).min(cols)
+            }
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                let blue = self.grid.cell(self.row, self.col);
+                self.grid.set_cell(self.row, self.col, !blue);
+            }
+            KeyCode::Char('s') => {
+                self.message =
+                    match fs::write(&self.file, self.grid.to_string()) {
+                        Ok(()) => format!("Saved to {}", self.file.display()),
+                        Err(err) => format!("Could not save: {}", err),
+                    };
+            }
+            KeyCode::Char('r') => match load(&self.file) {
+                Ok(grid) => {
+                    self.grid = grid;
+                    self.row = self.row.min(self.grid.get_rows()).max(1);
+                    self.col = self.col.min(self.grid.get_cols()).max(1);
+                    self.message = format!("Loaded {}", self.file.display());
+                }
+                Err(err) => self.message = format!("Could not load: {}", err),
+            },
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            _ => (),
+        }
+        true
+    }
+}
+
+fn load(file: &PathBuf) -> Result<Grid, String> {
+    let text = fs::read_to_string(file).map_err(|err| err.to_string())?;
+    let grid: Grid = text.parse()?;
+    if grid.get_rows() == 0 || grid.get_cols() == 0 {
+        return Err("The grid should not be empty".to_string());
+    }
+    Ok(grid)
+}
+
+fn run(editor: &mut Editor) -> io::Result<()> {
+    let mut out = io::stdout();
+    loop {
+        editor.draw(&mut out)?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !editor.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+pub fn main() {
+    let args = Args::parse();
+
+    let grid = if args.file.exists() {
+        load(&args.file).unwrap_or_else(|err| {
+            eprintln!("Could not load {}: {}", args.file.display(), err);
+            std::process::exit(1);
+        })
+    } else if args.rows == 0 || args.cols == 0 {
+        eprintln!("The grid should have at least one row and one column");
+        std::process::exit(1);
+    } else {
+        Grid::new_empty(args.rows, args.cols)
+    };
+    let mut editor = Editor {
+        grid,
+        file: args.file,
+        row: 1,
+        col: 1,
+        message: String::new(),
+    };
+
+    // Put the terminal back before printing a panic message, so that the
+    // message is visible and the terminal still works
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+
+    let result = terminal::enable_raw_mode()
+        .and_then(|()| execute!(io::stdout(), EnterAlternateScreen, Hide))
+        .and_then(|()| run(&mut editor));
+    if let Err(err) = result.and(restore_terminal()) {
+        eprintln!("Terminal error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn restore_terminal() -> io::Result<()> {
+    execute!(io::stdout(), Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str, file: PathBuf) -> Editor {
+        let grid = text.parse().unwrap();
+        Editor { grid, file, row: 1, col: 1, message: String::new() }
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "fish-friendly-edit-{}-{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    #[test]
+    fn test_cursor() {
+        let mut editor = editor("...\n...\n", PathBuf::new());
+        // The cursor stays inside the grid
+        assert!(editor.handle_key(KeyCode::Up));
+        assert!(editor.handle_key(KeyCode::Char('h')));
+        assert_eq!((editor.row, editor.col), (1, 1));
+        for _ in 0..5 {
+            editor.handle_key(KeyCode::Down);
+            editor.handle_key(KeyCode::Char('l'));
+        }
+        assert_eq!((editor.row, editor.col), (2, 3));
+        editor.handle_key(KeyCode::Char('k'));
+        editor.handle_key(KeyCode::Left);
+        assert_eq!((editor.row, editor.col), (1, 2));
+        assert!(!editor.handle_key(KeyCode::Char('q')));
+        assert!(!editor.handle_key(KeyCode::Esc));
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut editor = editor("B.\n", PathBuf::new());
+        assert!(!editor.grid.fish_friendly());
+        editor.handle_key(KeyCode::Right);
+        editor.handle_key(KeyCode::Char(' '));
+        assert!(editor.grid.fish_friendly());
+        editor.handle_key(KeyCode::Enter);
+        assert_eq!(editor.grid.to_string(), "B.\n");
+    }
+
+    #[test]
+    fn test_save_and_reload() {
+        let file = temp_file("save");
+        let mut editor = editor("B.\n.B\n", file.clone());
+        editor.handle_key(KeyCode::Char('s'));
+        assert!(editor.message.starts_with("Saved"));
+        assert_eq!(load(&file).unwrap().to_string(), "B.\n.B\n");
+        // Reloading discards the changes, and keeps the cursor in the grid
+        fs::write(&file, "B\n").unwrap();
+        editor.handle_key(KeyCode::Down);
+        editor.handle_key(KeyCode::Right);
+        editor.handle_key(KeyCode::Char('r'));
+        assert!(editor.message.starts_with("Loaded"));
+        assert_eq!(editor.grid.to_string(), "B\n");
+        assert_eq!((editor.row, editor.col), (1, 1));
+        fs::remove_file(&file).unwrap();
+        editor.handle_key(KeyCode::Char('r'));
+        assert!(editor.message.starts_with("Could not load"));
+        // Any other key clears the message
+        editor.handle_key(KeyCode::Tab);
+        assert!(editor.message.is_empty());
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let file = temp_file("load");
+        assert!(load(&file).is_err());
+        fs::write(&file, "B?\n").unwrap();
+        assert!(load(&file).is_err());
+        fs::write(&file, "\n").unwrap();
+        assert!(load(&file).is_err());
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_draw_status() {
+        let mut out = Vec::new();
+        editor("BB\n.B\n", PathBuf::new()).draw(&mut out).unwrap();
+        let text = String::from_utf8_lossy(&out);
+        assert!(text.contains("The fish can swim across (path of 2 cells"));
+        let mut out = Vec::new();
+        editor("B.\n.B\n", PathBuf::new()).draw(&mut out).unwrap();
+        let text = String::from_utf8_lossy(&out);
+        assert!(text.contains("The fish is blocked (chain of 2 water cells"));
+    }
+}
@@ -0,0 +1,76 @@
+/*
+    Binary to compute statistics of the minimum crossing cost,
+    when each cell has a random cost (first-passage percolation)
+*/
+
+use fish_friendly::energy::{
+    exhaustive_cost_stats, random_cost_stats, CostDistribution,
+};
+use fish_friendly::progress::ProgressBar;
+
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(help = "Number of rows")]
+    rows: usize,
+    #[arg(help = "Number of columns")]
+    cols: usize,
+    #[arg(
+        short,
+        long,
+        help = "Cost distribution as cost:weight pairs (x = impassable)",
+        default_value = "1:1,2:1"
+    )]
+    dist: CostDistribution,
+    #[arg(short, long, help = "Energy budget of the fish")]
+    budget: Option<u64>,
+    #[arg(
+        short,
+        long,
+        help = "Use this many random grids instead of all grids"
+    )]
+    random: Option<usize>,
+}
+
+pub fn main() {
+    let args = Args::parse();
+
+    let stats = match args.random {
+        Some(num_samples) => random_cost_stats(
+            args.rows,
+            args.cols,
+            &args.dist,
+            num_samples,
+            &mut ProgressBar::new(),
+        ),
+        None => exhaustive_cost_stats(
+            args.rows,
+            args.cols,
+            &args.dist,
+            &mut ProgressBar::new(),
+        ),
+    };
+
+    println!(
+        "=== Results for {} x {} grids with costs {} ===",
+        args.rows, args.cols, args.dist
+    );
+    println!("Minimum crossing cost distribution:");
+    for &cost in stats.histogram.keys() {
+        let label = cost.map_or("none".to_string(), |c| c.to_string());
+        println!("{:>8}: {:.6}", label, stats.probability(cost));
+    }
+    match stats.mean_cost() {
+        Some(mean) => println!("Mean minimum crossing cost: {:.3}", mean),
+        None => println!("The fish can never swim across."),
+    }
+    if let Some(budget) = args.budget {
+        println!(
+            "With budget {}, the fish can swim across in {:.3}% of cases.",
+            budget,
+            stats.probability_within(budget) * 100.0
+        );
+    }
+}
@@ -0,0 +1,45 @@
+/*
+    Binary to compute the exponential growth rate of the number of
+    fish-friendly grids with a fixed number of rows
+    (as the number of columns goes to infinity)
+*/
+
+use fish_friendly::transfer::{TransferMatrix, MAX_ROWS};
+
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(help = "Up to number of rows", default_value = "8")]
+    upto: usize,
+}
+
+pub fn main() {
+    let Args { upto } = Args::parse();
+    if upto > MAX_ROWS {
+        eprintln!("The number of rows should be at most {}", MAX_ROWS);
+        std::process::exit(1);
+    }
+
+    println!("=== Results ===");
+    for rows in 1..=upto {
+        let matrix = TransferMatrix::new(rows);
+        let (lo, hi) = matrix.growth_rate_bounds();
+        let total = 2_f64.powi(rows as i32);
+        println!(
+            "{} rows ({} states): growth rate in [{:.12}, {:.12}] vs. 2^{} = {}",
+            rows,
+            matrix.num_states(),
+            lo,
+            hi,
+            rows,
+            total,
+        );
+        println!(
+            "    Friendly fraction shrinks by a factor in [{:.12}, {:.12}] per column",
+            lo / total,
+            hi / total,
+        );
+    }
+}
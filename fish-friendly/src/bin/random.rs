@@ -2,13 +2,17 @@
     Binary to randomly generate grids & estimate the probability
 */
 
-use fish_friendly::Grid;
+use fish_friendly::cluster::ClusterSummary;
+use fish_friendly::progress::ProgressBar;
+use fish_friendly::render::{ImageFormat, RenderOptions};
+use fish_friendly::report::CountResult;
+use fish_friendly::{Algorithm, Grid, MOVE_RULE};
 
 use clap::Parser;
+use std::fs;
 use std::io::{self, Write};
-
-// Number of steps to display progress
-const PROGRESS_STEPS: usize = 10;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -24,49 +28,185 @@ struct Args {
         default_value = "1000000"
     )]
     num_iters: usize,
+    #[arg(long, help = "Directory to save images of the first few grids")]
+    images: Option<PathBuf>,
+    #[arg(long, help = "Number of images to save", default_value = "10")]
+    num_images: usize,
+    #[arg(long, help = "Image format (svg or ppm)", default_value = "svg")]
+    format: ImageFormat,
+    #[arg(long, help = "Cell size in images, in pixels", default_value = "20")]
+    cell_size: usize,
+    #[arg(long, help = "Color each connected component differently")]
+    components: bool,
+    #[arg(long, help = "Also report cluster statistics")]
+    clusters: bool,
+    #[arg(long, help = "Print the result as JSON")]
+    json: bool,
+    #[arg(
+        short,
+        long,
+        help = "Algorithm to check each grid",
+        default_value = "dfs"
+    )]
+    algorithm: Algorithm,
+    #[arg(long, help = "Also time both algorithms on the same grids")]
+    benchmark: bool,
+}
+
+// Grids generated at a time when benchmarking
+const BENCHMARK_BATCH: usize = 1000;
+
+// Time each algorithm on the same random grids, checking that they agree
+// (skipping any algorithm that doesn't apply under the move rule)
+fn benchmark(rows: usize, cols: usize, num_iters: usize, out: &mut dyn Write) {
+    let (algorithms, skipped): (Vec<Algorithm>, Vec<Algorithm>) =
+        [Algorithm::Dfs, Algorithm::UnionFind]
+            .into_iter()
+            .partition(|algorithm| algorithm.supported());
+    let mut times = vec![Duration::ZERO; algorithms.len()];
+    let mut done = 0;
+    while done < num_iters {
+        let batch = BENCHMARK_BATCH.min(num_iters - done);
+        let grids: Vec<Grid> =
+            (0..batch).map(|_| Grid::new_random(rows, cols)).collect();
+        let mut results = Vec::new();
+        for (time, &algorithm) in times.iter_mut().zip(&algorithms) {
+            let start = Instant::now();
+            let friendly: Vec<bool> = grids
+                .iter()
+                .map(|g| g.fish_friendly_using(algorithm))
+                .collect();
+            *time += start.elapsed();
+            results.push(friendly);
+        }
+        assert!(
+            results.windows(2).all(|w| w[0] == w[1]),
+            "The algorithms disagree"
+        );
+        done += batch;
+    }
+    writeln!(out, "=== Benchmark on {} grids ===", num_iters).unwrap();
+    for (time, algorithm) in times.iter().zip(&algorithms) {
+        writeln!(
+            out,
+            "{:>12}: {:.3?} total, {:.3?} per grid",
+            algorithm,
+            time,
+            time.div_f64(num_iters as f64),
+        )
+        .unwrap();
+    }
+    for algorithm in skipped {
+        writeln!(
+            out,
+            "{:>12}: skipped (does not support {:?})",
+            algorithm, MOVE_RULE,
+        )
+        .unwrap();
+    }
 }
 
 fn main() {
     let args = Args::parse();
 
-    if args.num_iters % PROGRESS_STEPS != 0 {
-        eprintln!(
-            "The number of iterations should be a multiple of {}",
-            PROGRESS_STEPS
+    if !args.json {
+        println!(
+            "Running {} iterations for {} x {} grids",
+            args.num_iters, args.rows, args.cols
         );
-        std::process::exit(1);
     }
-    let progress_step = args.num_iters / 10;
+    if let Some(dir) = &args.images {
+        fs::create_dir_all(dir).unwrap_or_else(|err| {
+            eprintln!("Could not create {}: {}", dir.display(), err);
+            std::process::exit(1);
+        });
+    }
+    let mut options =
+        RenderOptions::new(args.cell_size).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+    options.color_components = args.components;
 
-    println!(
-        "Running {} iterations for {} x {} grids",
-        args.num_iters, args.rows, args.cols
-    );
-    let mut friendly: usize = 0;
-    for epoch in 0..(args.num_iters / progress_step) {
-        print!(
-            "Running iterations {}-{}...",
-            epoch * progress_step,
-            (epoch + 1) * progress_step - 1,
-        );
-        io::stdout().flush().unwrap();
+    let mut summary = ClusterSummary::new();
+    let result = CountResult::sample(
+        args.rows,
+        args.cols,
+        args.num_iters,
+        args.algorithm,
+        &mut ProgressBar::new(),
+        |iter, grid| {
+            if args.clusters {
+                summary.add(&grid.cluster_stats());
+            }
+            if let Some(dir) = &args.images {
+                if iter < args.num_images {
+                    let file =
+                        format!("grid-{}.{}", iter, args.format.extension());
+                    let path = dir.join(file);
+                    if let Err(err) =
+                        grid.save_image(&path, args.format, &options)
+                    {
+                        eprintln!("Could not save {}: {}", path.display(), err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        },
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    // With --json, only the result goes to stdout, and the rest to stderr
+    let mut out: Box<dyn Write> = if args.json {
+        println!("{}", result.to_json());
+        Box::new(io::stderr())
+    } else {
+        println!("{}", result);
+        Box::new(io::stdout())
+    };
 
-        let mut new_friendly: usize = 0;
-        for _ in 0..progress_step {
-            let grid = Grid::new_random(args.rows, args.cols);
-            if grid.fish_friendly() {
-                new_friendly += 1;
+    if args.benchmark {
+        benchmark(args.rows, args.cols, args.num_iters, &mut out);
+    }
+
+    if args.clusters {
+        let cells = (args.rows * args.cols) as f64;
+        writeln!(out, "=== Cluster statistics ===").unwrap();
+        writeln!(
+            out,
+            "Mean number of clusters: {:.3}",
+            summary.mean_clusters()
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "Mean largest cluster: {:.3} ({:.3}% of cells)",
+            summary.mean_largest(),
+            summary.mean_largest() * 100.0 / cells,
+        )
+        .unwrap();
+        match summary.mean_finite_size() {
+            Some(mean) => {
+                writeln!(out, "Mean non-spanning cluster size: {:.3}", mean)
+            }
+            None => {
+                writeln!(out, "Mean non-spanning cluster size: (no clusters)")
             }
         }
-        println!(" {} friendly", new_friendly);
-        friendly += new_friendly;
+        .unwrap();
+        writeln!(out, "Cluster size distribution (clusters per grid):")
+            .unwrap();
+        for (size, count) in &summary.size_distribution {
+            writeln!(
+                out,
+                "{:8}: {:.6}",
+                size,
+                *count as f64 / summary.num_grids as f64
+            )
+            .unwrap();
+        }
     }
-
-    println!("=== Results for {} x {} grids ===", args.rows, args.cols);
-    println!(
-        "The fish can swim across in {}/{} cases ({:.3}%).",
-        friendly,
-        args.num_iters,
-        (friendly as f64) * 100.0 / (args.num_iters as f64),
-    );
 }
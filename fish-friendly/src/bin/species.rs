@@ -0,0 +1,70 @@
+/*
+    Binary to count grids with several species of fish
+    (each with its own color, plus optional shared colors)
+*/
+
+use fish_friendly::progress::ProgressBar;
+use fish_friendly::species::{count_multi_species_with, standard_species};
+
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(help = "Number of rows")]
+    rows: usize,
+    #[arg(help = "Number of columns")]
+    cols: usize,
+    #[arg(short, long, help = "Number of species", default_value = "2")]
+    species: usize,
+    #[arg(
+        long,
+        help = "Number of colors shared by all species",
+        default_value = "0"
+    )]
+    shared: usize,
+}
+
+pub fn main() {
+    let args = Args::parse();
+    if args.species == 0 {
+        eprintln!("There should be at least one species");
+        std::process::exit(1);
+    }
+
+    let (colors, masks) = standard_species(args.species, args.shared);
+    let result = count_multi_species_with(
+        args.rows,
+        args.cols,
+        colors,
+        &masks,
+        &mut ProgressBar::new(),
+    );
+
+    let percent = |x: u128| (x as f64) * 100.0 / (result.total as f64);
+    println!(
+        "=== Results for {} x {} grids with {} colors ({} species, {} shared) ===",
+        args.rows, args.cols, colors, args.species, args.shared
+    );
+    for (s, &count) in result.per_species.iter().enumerate() {
+        println!(
+            "Species {} can swim across in {} of {} cases ({:.3}%).",
+            s + 1,
+            count,
+            result.total,
+            percent(count),
+        );
+    }
+    println!(
+        "All species can swim across in {} of {} cases ({:.3}%).",
+        result.all,
+        result.total,
+        percent(result.all),
+    );
+    println!(
+        "At least one species can swim across in {} of {} cases ({:.3}%).",
+        result.any,
+        result.total,
+        percent(result.any),
+    );
+}
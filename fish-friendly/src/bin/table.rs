@@ -3,7 +3,8 @@
     (as a function of m and n)
 */
 
-use fish_friendly::count_friendly_grids;
+use fish_friendly::progress::{ProgressBar, Silent};
+use fish_friendly::report::{CountMethod, CountResult};
 
 use clap::Parser;
 
@@ -12,23 +13,46 @@ use clap::Parser;
 struct Args {
     #[arg(help = "Up to size (sum of rows + cols)")]
     upto: usize,
+    #[arg(
+        short,
+        long,
+        help = "Counting method (brute-force or transfer-matrix)",
+        default_value = "brute-force"
+    )]
+    method: CountMethod,
+    #[arg(short, long, help = "Print the results as JSON (one per line)")]
+    json: bool,
 }
 
 #[allow(clippy::needless_range_loop)]
 pub fn main() {
-    let Args { upto } = Args::parse();
+    let Args { upto, method, json } = Args::parse();
 
     let mut friendly_table = vec![vec![None; upto]; upto];
     let mut total_table = vec![vec![None; upto]; upto];
     for size in 1..upto {
         for rows in 1..size {
             let cols = size - rows;
-            println!("=== {} x {} grids ===", rows, cols);
-            let (friendly, total) = count_friendly_grids(rows, cols);
-            friendly_table[rows][cols] = Some(friendly);
-            total_table[rows][cols] = Some(total);
+            let result = if json {
+                CountResult::count(rows, cols, method, &mut Silent)
+            } else {
+                println!("=== {} x {} grids ===", rows, cols);
+                CountResult::count(rows, cols, method, &mut ProgressBar::new())
+            }
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            });
+            if json {
+                println!("{}", result.to_json());
+            }
+            friendly_table[rows][cols] = Some(result.friendly);
+            total_table[rows][cols] = Some(result.total);
         }
     }
+    if json {
+        return;
+    }
 
     println!("=== Results ===");
     println!("Friendly:");
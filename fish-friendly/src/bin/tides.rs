@@ -0,0 +1,83 @@
+/*
+    Binary to estimate the probability that the fish can cross
+    when cells flip between blue and water over time
+    (as a function of the flip rate)
+*/
+
+use fish_friendly::tides::{crossing_probability, Schedule};
+
+use clap::Parser;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(help = "Number of rows")]
+    rows: usize,
+    #[arg(help = "Number of columns")]
+    cols: usize,
+    #[arg(
+        short,
+        long,
+        help = "Number of samples per flip rate",
+        default_value = "10000"
+    )]
+    num_samples: usize,
+    #[arg(
+        long,
+        help = "Number of ticks to simulate (random schedule; default: 2 * rows * cols)"
+    )]
+    horizon: Option<usize>,
+    #[arg(long, help = "Random seed", default_value = "0")]
+    seed: u64,
+    #[arg(
+        long,
+        help = "Flip rates to try (random schedule)",
+        value_delimiter = ',',
+        default_value = "0,0.01,0.02,0.05,0.1,0.2,0.5"
+    )]
+    rates: Vec<f64>,
+    #[arg(long, help = "Use periodic schedules with periods 1..=N instead")]
+    periodic: Option<usize>,
+}
+
+pub fn main() {
+    let args = Args::parse();
+    if let Some(rate) = args.rates.iter().find(|r| !(0.0..=1.0).contains(*r)) {
+        eprintln!("Flip rates should be between 0 and 1 (got {})", rate);
+        std::process::exit(1);
+    }
+
+    let horizon = args.horizon.unwrap_or(2 * args.rows * args.cols);
+    let schedules: Vec<Schedule> = match args.periodic {
+        Some(max_period) => (1..=max_period)
+            .map(|period| Schedule::Periodic { period })
+            .collect(),
+        None => {
+            args.rates.iter().map(|&rate| Schedule::Random { rate }).collect()
+        }
+    };
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    println!(
+        "=== Results for {} x {} grids ({} samples each) ===",
+        args.rows, args.cols, args.num_samples
+    );
+    for schedule in &schedules {
+        let prob = crossing_probability(
+            args.rows,
+            args.cols,
+            schedule,
+            horizon,
+            args.num_samples,
+            &mut rng,
+        );
+        println!(
+            "{:?} (flip rate {:.3}): the fish can swim across in {:.3}% of cases.",
+            schedule,
+            schedule.flip_rate(),
+            prob * 100.0
+        );
+    }
+}
@@ -0,0 +1,204 @@
+/*
+    Cluster analysis: the connected components of blue cells
+
+    A cluster is spanning if it touches both the first and the last column.
+    Following the usual convention in percolation theory, the mean cluster
+    size is the expected size of the cluster containing a random blue cell,
+    excluding spanning clusters:
+        sum_s s^2 n_s / sum_s s n_s
+    where n_s is the number of non-spanning clusters of size s.
+*/
+
+use super::Grid;
+
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug)]
+pub struct ClusterStats {
+    // Size of each cluster, in the order of Grid::components
+    pub sizes: Vec<usize>,
+    pub spanning: Vec<bool>,
+}
+
+impl ClusterStats {
+    pub fn num_clusters(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn num_spanning(&self) -> usize {
+        self.spanning.iter().filter(|&&b| b).count()
+    }
+
+    pub fn largest(&self) -> usize {
+        self.sizes.iter().cloned().max().unwrap_or(0)
+    }
+
+    // Cluster size -> number of clusters of that size
+    pub fn size_distribution(&self) -> BTreeMap<usize, usize> {
+        let mut result = BTreeMap::new();
+        for &size in &self.sizes {
+            *result.entry(size).or_insert(0) += 1;
+        }
+        result
+    }
+
+    fn finite_sizes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.sizes
+            .iter()
+            .zip(&self.spanning)
+            .filter(|&(_, &s)| !s)
+            .map(|(&n, _)| n)
+    }
+
+    // (sum_s s^2 n_s, sum_s s n_s) over the non-spanning clusters
+    fn finite_moments(&self) -> (usize, usize) {
+        let weighted = self.finite_sizes().map(|s| s * s).sum();
+        let weight = self.finite_sizes().sum();
+        (weighted, weight)
+    }
+
+    // None if all clusters are spanning
+    pub fn mean_finite_size(&self) -> Option<f64> {
+        mean_size(self.finite_moments())
+    }
+}
+
+fn mean_size((weighted, weight): (usize, usize)) -> Option<f64> {
+    if weight == 0 {
+        None
+    } else {
+        Some(weighted as f64 / weight as f64)
+    }
+}
+
+impl Grid {
+    #[allow(clippy::needless_range_loop)]
+    pub fn cluster_stats(&self) -> ClusterStats {
+        let (labels, num_clusters) = self.components();
+        let mut sizes = vec![0; num_clusters];
+        let mut left = vec![false; num_clusters];
+        let mut right = vec![false; num_clusters];
+        for i in 1..=self.rows {
+            for j in 1..=self.cols {
+                if let Some(k) = labels[i][j] {
+                    sizes[k] += 1;
+                    left[k] |= j == 1;
+                    right[k] |= j == self.cols;
+                }
+            }
+        }
+        let spanning = left.iter().zip(&right).map(|(&l, &r)| l && r).collect();
+        ClusterStats { sizes, spanning }
+    }
+}
+
+/*
+    Aggregate statistics over many grids (of the same size)
+*/
+#[derive(Clone, Debug, Default)]
+pub struct ClusterSummary {
+    pub num_grids: usize,
+    pub num_spanning_grids: usize,
+    pub total_clusters: usize,
+    pub total_largest: usize,
+    // Cluster size -> total number of clusters of that size
+    pub size_distribution: BTreeMap<usize, usize>,
+    // Sums of s^2 n_s and of s n_s over all grids (see ClusterStats)
+    total_finite_weighted: usize,
+    total_finite_weight: usize,
+}
+
+impl ClusterSummary {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add(&mut self, stats: &ClusterStats) {
+        self.num_grids += 1;
+        if stats.num_spanning() > 0 {
+            self.num_spanning_grids += 1;
+        }
+        self.total_clusters += stats.num_clusters();
+        self.total_largest += stats.largest();
+        for (size, count) in stats.size_distribution() {
+            *self.size_distribution.entry(size).or_insert(0) += count;
+        }
+        let (weighted, weight) = stats.finite_moments();
+        self.total_finite_weighted += weighted;
+        self.total_finite_weight += weight;
+    }
+
+    pub fn mean_clusters(&self) -> f64 {
+        self.total_clusters as f64 / self.num_grids as f64
+    }
+
+    pub fn mean_largest(&self) -> f64 {
+        self.total_largest as f64 / self.num_grids as f64
+    }
+
+    // The same statistic as ClusterStats::mean_finite_size, with the
+    // clusters of all grids pooled together (not a mean of the per-grid
+    // means, which would give grids with few blue cells too much weight)
+    pub fn mean_finite_size(&self) -> Option<f64> {
+        mean_size((self.total_finite_weighted, self.total_finite_weight))
+    }
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster_stats() {
+        let grid: Grid = "BB.B\n...B\nB.BB\n".parse().unwrap();
+        let stats = grid.cluster_stats();
+        let mut clusters: Vec<(usize, bool)> =
+            stats.sizes.iter().cloned().zip(stats.spanning.clone()).collect();
+        clusters.sort_unstable();
+        assert_eq!(clusters, vec![(1, false), (2, false), (4, false)]);
+        assert_eq!(stats.num_clusters(), 3);
+        assert_eq!(stats.num_spanning(), 0);
+        assert_eq!(stats.largest(), 4);
+        let expected: BTreeMap<usize, usize> =
+            [(1, 1), (2, 1), (4, 1)].into_iter().collect();
+        assert_eq!(stats.size_distribution(), expected);
+        // (1 + 4 + 16) / (1 + 2 + 4)
+        assert_eq!(stats.mean_finite_size(), Some(3.0));
+    }
+
+    #[test]
+    fn test_spanning() {
+        let grid: Grid = "BBB\n...\nB..\n".parse().unwrap();
+        let stats = grid.cluster_stats();
+        assert_eq!(stats.num_spanning(), 1);
+        assert_eq!(stats.mean_finite_size(), Some(1.0));
+        let grid: Grid = "BBB\n...\n".parse().unwrap();
+        assert_eq!(grid.cluster_stats().mean_finite_size(), None);
+        let grid: Grid = "...\n".parse().unwrap();
+        let stats = grid.cluster_stats();
+        assert_eq!((stats.num_clusters(), stats.largest()), (0, 0));
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut summary = ClusterSummary::new();
+        assert_eq!(summary.mean_finite_size(), None);
+        for text in ["BBB\n...\n", "B.B\n...\n", "BB.\n...\n"] {
+            let grid: Grid = text.parse().unwrap();
+            summary.add(&grid.cluster_stats());
+        }
+        assert_eq!(summary.num_grids, 3);
+        assert_eq!(summary.num_spanning_grids, 1);
+        assert_eq!(summary.mean_clusters(), 4.0 / 3.0);
+        assert_eq!(summary.mean_largest(), 2.0);
+        let expected: BTreeMap<usize, usize> =
+            [(1, 2), (2, 1), (3, 1)].into_iter().collect();
+        assert_eq!(summary.size_distribution, expected);
+        // Pooled over the clusters of size 1, 1 and 2: (1 + 1 + 4) / 4,
+        // rather than the mean of the per-grid means (1 + 2) / 2
+        assert_eq!(summary.mean_finite_size(), Some(1.5));
+    }
+}
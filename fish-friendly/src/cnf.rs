@@ -0,0 +1,275 @@
+/*
+    CNF encoding of the fish-friendly property, for cross-checking counts
+    with independent model counters.
+
+    Variables 1..=rows*cols are the grid cells (true = blue), row by row.
+    The remaining variables encode reachability in a fixed number of steps:
+        r[0][i][j] <-> blue(i, j) and j is the first column
+        r[t+1][i][j] <-> blue(i, j) and (r[t][i][j] or r[t][n], n a neighbor)
+    where the fish can move from each neighbor n to (i, j) under MOVE_RULE.
+    Since a shortest path visits each cell at most once, rows*cols - 1 steps
+    are enough. Every auxiliary variable is defined by an equivalence, so it
+    is functionally determined by the cells, and the number of models equals
+    the number of (un)friendly grids exactly.
+*/
+
+use super::MOVE_RULE;
+
+use std::io::{self, Write};
+
+pub type Lit = i32;
+
+pub struct Cnf {
+    num_vars: usize,
+    clauses: Vec<Vec<Lit>>,
+    // Variables that the models should be projected onto
+    show: Vec<usize>,
+    comment: String,
+}
+
+impl Cnf {
+    pub fn get_num_vars(&self) -> usize {
+        self.num_vars
+    }
+
+    pub fn get_num_clauses(&self) -> usize {
+        self.clauses.len()
+    }
+
+    pub fn get_clauses(&self) -> &[Vec<Lit>] {
+        &self.clauses
+    }
+
+    // DIMACS format, with the projection written both as "c p show"
+    // (model counting competition) and "c ind" (used by some other tools)
+    pub fn write_dimacs<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "c {}", self.comment)?;
+        for header in ["c p show", "c ind"] {
+            write!(out, "{}", header)?;
+            for v in &self.show {
+                write!(out, " {}", v)?;
+            }
+            writeln!(out, " 0")?;
+        }
+        writeln!(out, "p cnf {} {}", self.num_vars, self.clauses.len())?;
+        for clause in &self.clauses {
+            for lit in clause {
+                write!(out, "{} ", lit)?;
+            }
+            writeln!(out, "0")?;
+        }
+        Ok(())
+    }
+
+    // Exact model count (over all variables), by a simple DPLL search.
+    // Only intended for tiny formulas, to test the encoding.
+    pub fn count_models(&self) -> u128 {
+        let mut assignment = vec![None; self.num_vars + 1];
+        self.count_models_rec(&mut assignment)
+    }
+
+    fn lit_value(assignment: &[Option<bool>], lit: Lit) -> Option<bool> {
+        assignment[lit.unsigned_abs() as usize].map(|b| b == (lit > 0))
+    }
+
+    fn count_models_rec(&self, assignment: &mut Vec<Option<bool>>) -> u128 {
+        let saved = assignment.clone();
+        // Unit propagation
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for clause in &self.clauses {
+                let mut satisfied = false;
+                let mut unassigned = None;
+                let mut num_unassigned = 0;
+                for &lit in clause {
+                    match Self::lit_value(assignment, lit) {
+                        Some(true) => {
+                            satisfied = true;
+                            break;
+                        }
+                        Some(false) => (),
+                        None => {
+                            num_unassigned += 1;
+                            unassigned = Some(lit);
+                        }
+                    }
+                }
+                if satisfied {
+                    continue;
+                } else if num_unassigned == 0 {
+                    *assignment = saved;
+                    return 0;
+                } else if num_unassigned == 1 {
+                    let lit = unassigned.unwrap();
+                    assignment[lit.unsigned_abs() as usize] = Some(lit > 0);
+                    changed = true;
+                }
+            }
+        }
+        // Branch on the first unassigned variable
+        let result =
+            match (1..=self.num_vars).find(|&v| assignment[v].is_none()) {
+                None => 1,
+                Some(v) => {
+                    let mut result = 0;
+                    for b in [false, true] {
+                        assignment[v] = Some(b);
+                        result += self.count_models_rec(assignment);
+                    }
+                    result
+                }
+            };
+        *assignment = saved;
+        result
+    }
+}
+
+// Encode the rows x cols grids that are friendly (or unfriendly)
+pub fn encode(rows: usize, cols: usize, friendly: bool) -> Cnf {
+    assert!(rows > 0 && cols > 0, "Grid should be nonempty");
+    let cells = rows * cols;
+    let steps = cells.saturating_sub(1);
+    let blue = |i: usize, j: usize| (i * cols + j + 1) as Lit;
+    let reach = |t: usize, i: usize, j: usize| {
+        (cells * (t + 1) + i * cols + j + 1) as Lit
+    };
+    // Cells the fish can move to (i, j) from, with the same moves as
+    // Grid::adjacencies (a backwards step into (i, j) comes from j + 1)
+    let neighbors = |i: usize, j: usize| {
+        let mut result = Vec::new();
+        if i > 0 {
+            result.push((i - 1, j));
+        }
+        if i + 1 < rows {
+            result.push((i + 1, j));
+        }
+        if j > 0 {
+            result.push((i, j - 1));
+        }
+        if j + 1 < cols && MOVE_RULE.backwards() {
+            result.push((i, j + 1));
+        }
+        result
+    };
+
+    let mut clauses = Vec::new();
+    for i in 0..rows {
+        for j in 0..cols {
+            let r = reach(0, i, j);
+            if j == 0 {
+                clauses.push(vec![-r, blue(i, j)]);
+                clauses.push(vec![r, -blue(i, j)]);
+            } else {
+                clauses.push(vec![-r]);
+            }
+        }
+    }
+    for t in 0..steps {
+        for i in 0..rows {
+            for j in 0..cols {
+                // r <-> b and (p_1 or ... or p_k)
+                let r = reach(t + 1, i, j);
+                let b = blue(i, j);
+                let mut prev = vec![reach(t, i, j)];
+                prev.extend(
+                    neighbors(i, j).iter().map(|&(k, l)| reach(t, k, l)),
+                );
+                clauses.push(vec![-r, b]);
+                let mut clause = vec![-r];
+                clause.extend(&prev);
+                clauses.push(clause);
+                for p in prev {
+                    clauses.push(vec![r, -b, -p]);
+                }
+            }
+        }
+    }
+    let last = (0..rows).map(|i| reach(steps, i, cols - 1));
+    if friendly {
+        clauses.push(last.collect());
+    } else {
+        for r in last {
+            clauses.push(vec![-r]);
+        }
+    }
+
+    Cnf {
+        num_vars: cells * (steps + 2),
+        clauses,
+        show: (1..=cells).collect(),
+        comment: format!(
+            "{} {} x {} grids",
+            if friendly { "Friendly" } else { "Unfriendly" },
+            rows,
+            cols
+        ),
+    }
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Grid;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // Fix the cell variables to a particular grid
+    fn with_grid(mut cnf: Cnf, grid: &Grid) -> Cnf {
+        let cols = grid.get_cols();
+        for i in 1..=grid.get_rows() {
+            for j in 1..=cols {
+                let v = ((i - 1) * cols + j) as Lit;
+                cnf.clauses.push(vec![if grid.cell(i, j) { v } else { -v }]);
+            }
+        }
+        cnf
+    }
+
+    #[test]
+    fn test_single_grid() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..50 {
+            let grid = Grid::new_random_with(3, 3, &mut rng);
+            let friendly = with_grid(encode(3, 3, true), &grid);
+            let unfriendly = with_grid(encode(3, 3, false), &grid);
+            // The auxiliary variables are determined by the cells
+            let expected = grid.fish_friendly() as u128;
+            assert_eq!(friendly.count_models(), expected, "\n{}", grid);
+            assert_eq!(unfriendly.count_models(), 1 - expected, "\n{}", grid);
+        }
+    }
+
+    #[test]
+    fn test_count_models() {
+        let cnf = Cnf {
+            num_vars: 3,
+            clauses: vec![vec![1, 2], vec![-1, -2]],
+            show: vec![1, 2],
+            comment: String::new(),
+        };
+        // x1 xor x2, with x3 free
+        assert_eq!(cnf.count_models(), 4);
+        let cnf = Cnf { clauses: vec![vec![1], vec![-1]], ..cnf };
+        assert_eq!(cnf.count_models(), 0);
+    }
+
+    #[test]
+    fn test_write_dimacs() {
+        let cnf = encode(1, 2, true);
+        assert_eq!(cnf.get_num_vars(), 2 * 3);
+        let mut out = Vec::new();
+        cnf.write_dimacs(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "c Friendly 1 x 2 grids");
+        assert_eq!(lines[1], "c p show 1 2 0");
+        assert_eq!(lines[2], "c ind 1 2 0");
+        assert_eq!(lines[3], format!("p cnf 6 {}", cnf.get_num_clauses()));
+        assert_eq!(lines.len(), 4 + cnf.get_num_clauses());
+        assert!(lines[4..].iter().all(|line| line.ends_with(" 0")));
+    }
+}
@@ -0,0 +1,172 @@
+/*
+    Grids whose blue cells are connected
+
+    Counts grids in which the blue cells form a single connected component
+    (a polyomino inside the board; the all-water grid does not count), and
+    how many of those are also fish-friendly.
+
+    The brute force version checks every grid. The DP version reads the grid
+    one column at a time, like the transfer matrix (see transfer.rs), keeping
+    the component labels of the current column. Since all blue cells must end
+    up in one component, a component that stops (has no blue cell in the next
+    column) must be the only one, and every later column must be water.
+    A connected grid is then friendly exactly when its first and last
+    columns both have a blue cell. This needs backwards steps (as in
+    transfer.rs), so the DP version checks that MOVE_RULE allows them.
+*/
+
+use super::progress::{ProgressObserver, Silent, Tracker};
+use super::transfer::merge_column;
+use super::{Grid, MOVE_RULE};
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConnectedCount {
+    // Number of grids whose blue cells are connected
+    pub connected: u128,
+    // Number of those grids that are also fish-friendly
+    pub friendly: u128,
+    pub total: u128,
+}
+
+impl Grid {
+    // Check if the blue cells form a single connected component
+    pub fn blue_connected(&self) -> bool {
+        self.components().1 == 1
+    }
+}
+
+// Count connected grids by checking all of them, without reporting progress
+pub fn count_connected_grids(rows: usize, cols: usize) -> ConnectedCount {
+    count_connected_grids_with(rows, cols, &mut Silent)
+}
+
+// Count connected grids by checking all of them, reporting progress (with
+// the number of connected friendly grids) to the observer
+pub fn count_connected_grids_with<O: ProgressObserver + ?Sized>(
+    rows: usize,
+    cols: usize,
+    observer: &mut O,
+) -> ConnectedCount {
+    let expect_total: u128 = 2_u128
+        .checked_pow((rows * cols) as u32)
+        .expect("The number of grids is too large to fit in a u128");
+    let mut tracker = Tracker::new(observer, expect_total);
+
+    let mut result = ConnectedCount::default();
+    let mut grid = Grid::new_empty(rows, cols);
+    loop {
+        if grid.blue_connected() {
+            result.connected += 1;
+            if grid.fish_friendly() {
+                result.friendly += 1;
+            }
+        }
+        result.total += 1;
+        tracker.tick(result.total, result.friendly);
+        if !grid.rotate() {
+            break;
+        }
+    }
+    debug_assert_eq!(result.total, expect_total);
+    tracker.finish(result.total, result.friendly);
+
+    result
+}
+
+// State after reading some columns
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Profile {
+    // No blue cells yet
+    Empty,
+    // Component labels of the current column: 0 for water, then 1, 2, ...
+    // in order of first appearance. Also whether the first column had a
+    // blue cell.
+    Open { labels: Vec<u8>, touches_left: bool },
+    // The blue cells formed a single component, which has stopped
+    Closed,
+}
+
+// Add one column (bit i of column = row i is blue) to the right of the
+// current column's labels. Returns the new labels, and whether some
+// component stopped (has no blue cell in the new column).
+fn step(labels: &[u8], column: u32) -> (Vec<u8>, bool) {
+    let (new, old) = merge_column(labels, column);
+    let mut result = vec![0; labels.len()];
+    let mut new_labels: HashMap<usize, u8> = HashMap::new();
+    for (cell, root) in result.iter_mut().zip(&new) {
+        if let Some(root) = root {
+            let next_label = new_labels.len() as u8 + 1;
+            *cell = *new_labels.entry(*root).or_insert(next_label);
+        }
+    }
+    let stopped = old.values().any(|root| !new_labels.contains_key(root));
+    (result, stopped)
+}
+
+// Count connected grids column by column. This is exponential in the number
+// of rows but only linear in the number of columns.
+pub fn count_connected_dp(rows: usize, cols: usize) -> ConnectedCount {
+    assert!(
+        MOVE_RULE.backwards(),
+        "The column-by-column count assumes backwards steps are allowed"
+    );
+    assert!(rows < 32, "Too many rows for the column-by-column count");
+    let total = 2_u128
+        .checked_pow((rows * cols) as u32)
+        .expect("The number of grids is too large to fit in a u128");
+    let mut counts: HashMap<Profile, u128> = HashMap::new();
+    counts.insert(Profile::Empty, 1);
+    for j in 0..cols {
+        let mut next: HashMap<Profile, u128> = HashMap::new();
+        for column in 0..(1_u32 << rows) {
+            for (profile, &count) in &counts {
+                let succ = match profile {
+                    Profile::Empty if column == 0 => Some(Profile::Empty),
+                    Profile::Empty => Some(Profile::Open {
+                        labels: step(&vec![0; rows], column).0,
+                        touches_left: j == 0,
+                    }),
+                    Profile::Open { labels, touches_left } => {
+                        let (succ, stopped) = step(labels, column);
+                        let single = labels.iter().all(|&l| l <= 1);
+                        if !stopped {
+                            Some(Profile::Open {
+                                labels: succ,
+                                touches_left: *touches_left,
+                            })
+                        } else if single && column == 0 {
+                            Some(Profile::Closed)
+                        } else {
+                            None
+                        }
+                    }
+                    Profile::Closed if column == 0 => Some(Profile::Closed),
+                    Profile::Closed => None,
+                };
+                if let Some(succ) = succ {
+                    *next.entry(succ).or_insert(0) += count;
+                }
+            }
+        }
+        counts = next;
+    }
+
+    let mut result = ConnectedCount { total, ..Default::default() };
+    for (profile, &count) in &counts {
+        match profile {
+            Profile::Open { labels, touches_left }
+                if labels.iter().all(|&l| l <= 1) =>
+            {
+                result.connected += count;
+                if *touches_left {
+                    result.friendly += count;
+                }
+            }
+            Profile::Closed => result.connected += count,
+            _ => (),
+        }
+    }
+    result
+}
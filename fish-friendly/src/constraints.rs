@@ -0,0 +1,249 @@
+/*
+    Path constraints
+
+    Variants of the problem where the fish's path must also:
+        - pass through every one of a set of waypoints (marked cells),
+        - never turn left (relative to the direction it is swimming), or
+        - visit at most max_length cells in total.
+
+    These are not properties of the set of reachable cells, so we search a
+    state-augmented graph instead: each node is a position together with the
+    direction of the last step, the number of cells visited so far, and the
+    set of waypoints visited so far. The moves are the same as
+    Grid::adjacencies. The fish enters the first column from the left bank,
+    swimming right, and its path may revisit cells.
+*/
+
+use super::progress::{ProgressObserver, Silent, Tracker};
+use super::{dfs, dfs_path, Grid};
+
+// Waypoints visited so far are stored as a bitmask
+pub const MAX_WAYPOINTS: usize = 64;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    // Direction of a step from (i, j) to an adjacent cell (r, c)
+    fn of_step(i: usize, j: usize, r: usize, c: usize) -> Self {
+        if r < i {
+            Direction::Up
+        } else if r > i {
+            Direction::Down
+        } else if c < j {
+            Direction::Left
+        } else {
+            Direction::Right
+        }
+    }
+
+    // The direction after turning left
+    pub fn left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn reverse(self) -> Self {
+        self.left().left()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Constraints {
+    // Cells (indexed like Grid) the path must pass through
+    pub waypoints: Vec<(usize, usize)>,
+    // Forbid left turns (and U-turns, which would be two left turns)
+    pub no_left_turns: bool,
+    // Maximum number of cells on the path, including the first and last
+    pub max_length: Option<usize>,
+}
+
+impl Constraints {
+    pub fn is_unconstrained(&self) -> bool {
+        self.waypoints.is_empty()
+            && !self.no_left_turns
+            && self.max_length.is_none()
+    }
+
+    fn all_waypoints(&self) -> u64 {
+        assert!(
+            self.waypoints.len() <= MAX_WAYPOINTS,
+            "Too many waypoints: {} (max {})",
+            self.waypoints.len(),
+            MAX_WAYPOINTS
+        );
+        (0..self.waypoints.len()).fold(0, |acc, k| acc | (1 << k))
+    }
+
+    // Waypoints visited after arriving at (i, j)
+    fn visit(&self, visited: u64, i: usize, j: usize) -> u64 {
+        self.waypoints
+            .iter()
+            .enumerate()
+            .filter(|&(_, &cell)| cell == (i, j))
+            .fold(visited, |acc, (k, _)| acc | (1 << k))
+    }
+
+    fn allows_turn(&self, from: Direction, to: Direction) -> bool {
+        !self.no_left_turns || (to != from.left() && to != from.reverse())
+    }
+
+    fn allows_length(&self, length: usize) -> bool {
+        self.max_length.is_none_or(|max| length <= max)
+    }
+}
+
+// A node in the state-augmented graph
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct State {
+    i: usize,
+    j: usize,
+    // Direction of the last step
+    heading: Direction,
+    // Number of cells visited, counting repeats (0 if the length is not
+    // constrained, to keep the graph small)
+    length: usize,
+    // Bitmask of waypoints visited
+    visited: u64,
+}
+
+// The search, borrowing the grid and constraints
+struct Search<'a> {
+    grid: &'a Grid,
+    constraints: &'a Constraints,
+    all_waypoints: u64,
+}
+
+impl<'a> Search<'a> {
+    fn new(grid: &'a Grid, constraints: &'a Constraints) -> Self {
+        let all_waypoints = constraints.all_waypoints();
+        Self { grid, constraints, all_waypoints }
+    }
+
+    fn length_after(&self, length: usize) -> usize {
+        if self.constraints.max_length.is_some() {
+            length + 1
+        } else {
+            0
+        }
+    }
+
+    fn sources(&self) -> impl Iterator<Item = State> + '_ {
+        self.grid
+            .sources()
+            .map(|(i, j)| State {
+                i,
+                j,
+                heading: Direction::Right,
+                length: self.length_after(0),
+                visited: self.constraints.visit(0, i, j),
+            })
+            .filter(|s| self.constraints.allows_length(s.length))
+    }
+
+    fn successors(&self, s: State) -> impl Iterator<Item = State> + '_ {
+        self.grid
+            .adjacencies(s.i, s.j)
+            .map(move |(r, c)| State {
+                i: r,
+                j: c,
+                heading: Direction::of_step(s.i, s.j, r, c),
+                length: self.length_after(s.length),
+                visited: self.constraints.visit(s.visited, r, c),
+            })
+            .filter(move |next| {
+                self.constraints.allows_turn(s.heading, next.heading)
+                    && self.constraints.allows_length(next.length)
+            })
+    }
+
+    fn is_sink(&self, s: &State) -> bool {
+        self.grid.is_sink(s.i, s.j) && s.visited == self.all_waypoints
+    }
+}
+
+impl Grid {
+    // Check if the fish can get across with a path satisfying the constraints
+    pub fn fish_friendly_with(&self, constraints: &Constraints) -> bool {
+        let search = Search::new(self, constraints);
+        dfs(
+            search.sources(),
+            |s| search.successors(s.clone()),
+            |s| search.is_sink(s),
+        )
+    }
+
+    // A path the fish can take across satisfying the constraints, if any
+    pub fn fish_path_with(
+        &self,
+        constraints: &Constraints,
+    ) -> Option<Vec<(usize, usize)>> {
+        let search = Search::new(self, constraints);
+        let path = dfs_path(
+            search.sources(),
+            |s| search.successors(s.clone()),
+            |s| search.is_sink(s),
+        )?;
+        Some(path.into_iter().map(|s| (s.i, s.j)).collect())
+    }
+}
+
+// Count grids that are friendly under each set of constraints, without
+// reporting progress
+// Returns (friendly under each set of constraints, total)
+pub fn count_constrained_grids(
+    rows: usize,
+    cols: usize,
+    constraints: &[Constraints],
+) -> (Vec<u128>, u128) {
+    count_constrained_grids_with(rows, cols, constraints, &mut Silent)
+}
+
+// Count grids that are friendly under each set of constraints, reporting
+// progress (with the number of friendly grids under the first set of
+// constraints) to the observer
+// Returns (friendly under each set of constraints, total)
+pub fn count_constrained_grids_with<O: ProgressObserver + ?Sized>(
+    rows: usize,
+    cols: usize,
+    constraints: &[Constraints],
+    observer: &mut O,
+) -> (Vec<u128>, u128) {
+    let expect_total: u128 = 2_u128
+        .checked_pow((rows * cols) as u32)
+        .expect("The number of grids is too large to fit in a u128");
+    let mut tracker = Tracker::new(observer, expect_total);
+
+    let mut total: u128 = 0;
+    let mut friendly: Vec<u128> = vec![0; constraints.len()];
+    let mut grid = Grid::new_empty(rows, cols);
+    loop {
+        // Every constraint only removes paths, so skip the search when the
+        // grid isn't friendly at all
+        if grid.fish_friendly() {
+            for (count, c) in friendly.iter_mut().zip(constraints) {
+                if c.is_unconstrained() || grid.fish_friendly_with(c) {
+                    *count += 1;
+                }
+            }
+        }
+        total += 1;
+        tracker.tick(total, friendly.first().cloned().unwrap_or(0));
+        if !grid.rotate() {
+            break;
+        }
+    }
+    debug_assert_eq!(total, expect_total);
+    tracker.finish(total, friendly.first().cloned().unwrap_or(0));
+
+    (friendly, total)
+}
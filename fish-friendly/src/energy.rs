@@ -0,0 +1,424 @@
+/*
+    Weighted cells: the fish has an energy budget
+
+    Each cell has a small integer cost that the fish pays to swim into it
+    (including the first cell), or is impassable. The minimum crossing cost
+    is the cheapest path from the first column to the last column, found with
+    Dijkstra's algorithm; the fish can cross if this is at most its budget.
+    With costs drawn independently at random, this is first-passage
+    percolation on the grid.
+
+    A Grid is the special case where blue cells cost 1 and water is
+    impassable, so the minimum crossing cost is the length of the shortest
+    crossing path.
+*/
+
+use super::progress::{ProgressObserver, Tracker};
+use super::Grid;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fmt;
+use std::str::FromStr;
+
+// Cost of a cell, or None if impassable
+pub type Cost = Option<u32>;
+
+/*
+    Distribution of the cost of each cell: a list of costs with integer
+    weights.
+
+    Text format: comma-separated cost:weight pairs, with x for impassable,
+    e.g. "1:1,2:1,x:1".
+*/
+#[derive(Clone, Debug)]
+pub struct CostDistribution {
+    costs: Vec<Cost>,
+    weights: Vec<u32>,
+}
+
+impl CostDistribution {
+    pub fn new(costs: Vec<Cost>, weights: Vec<u32>) -> Self {
+        assert_eq!(costs.len(), weights.len());
+        assert!(weights.iter().any(|&w| w > 0), "All weights are zero");
+        Self { costs, weights }
+    }
+
+    pub fn total_weight(&self) -> u128 {
+        self.weights.iter().map(|&w| w as u128).sum()
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Cost {
+        self.sample_with(&self.index(), rng)
+    }
+
+    // Faster when drawing many samples
+    fn index(&self) -> WeightedIndex<u32> {
+        WeightedIndex::new(&self.weights).unwrap()
+    }
+    fn sample_with<R: Rng>(
+        &self,
+        index: &WeightedIndex<u32>,
+        rng: &mut R,
+    ) -> Cost {
+        self.costs[index.sample(rng)]
+    }
+}
+
+impl FromStr for CostDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut costs = Vec::new();
+        let mut weights = Vec::new();
+        for part in s.split(',') {
+            let (cost, weight) = part.split_once(':').ok_or_else(|| {
+                format!("Expected cost:weight, got {:?}", part)
+            })?;
+            let cost = match cost.trim() {
+                "x" => None,
+                c => Some(c.parse().map_err(|_| format!("Bad cost {:?}", c))?),
+            };
+            let weight = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("Bad weight {:?}", weight))?;
+            costs.push(cost);
+            weights.push(weight);
+        }
+        if !weights.iter().any(|&w| w > 0) {
+            return Err("All weights are zero".to_string());
+        }
+        Ok(Self { costs, weights })
+    }
+}
+
+impl fmt::Display for CostDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (k, (cost, weight)) in
+            self.costs.iter().zip(&self.weights).enumerate()
+        {
+            if k > 0 {
+                write!(f, ",")?;
+            }
+            match cost {
+                Some(c) => write!(f, "{}:{}", c, weight)?,
+                None => write!(f, "x:{}", weight)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/*
+    CostGrid struct (indexed like Grid, with an impassable border)
+*/
+pub struct CostGrid {
+    grid: Grid<Cost>,
+}
+
+impl CostGrid {
+    pub fn new_impassable(rows: usize, cols: usize) -> Self {
+        Self { grid: Grid::new_with_border(rows, cols, None) }
+    }
+
+    pub fn new_random(
+        rows: usize,
+        cols: usize,
+        dist: &CostDistribution,
+    ) -> Self {
+        let mut rng = rand::thread_rng();
+        let index = dist.index();
+        let mut result = Self::new_impassable(rows, cols);
+        for i in 1..=rows {
+            for j in 1..=cols {
+                result.set_cell(i, j, dist.sample_with(&index, &mut rng));
+            }
+        }
+        result
+    }
+
+    pub fn cell(&self, i: usize, j: usize) -> Cost {
+        self.grid.grid[i][j]
+    }
+
+    pub fn set_cell(&mut self, i: usize, j: usize, cost: Cost) {
+        self.grid.grid[i][j] = cost;
+    }
+
+    // Same moves as Grid::adjacencies, with the cost of each
+    fn adjacencies(
+        &self,
+        i: usize,
+        j: usize,
+    ) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
+        self.grid
+            .adjacencies_where(i, j, |cost| cost.is_some())
+            .filter_map(|(r, c)| self.cell(r, c).map(|cost| (r, c, cost)))
+    }
+
+    // Minimum total cost of a crossing path, or None if there is none
+    #[allow(clippy::needless_range_loop)]
+    pub fn min_crossing_cost(&self) -> Option<u64> {
+        let (rows, cols) = (self.grid.get_rows(), self.grid.get_cols());
+        let mut best = vec![vec![u64::MAX; cols + 2]; rows + 2];
+        let mut heap = BinaryHeap::new();
+        for i in 1..=rows {
+            if let Some(cost) = self.cell(i, 1) {
+                best[i][1] = cost as u64;
+                heap.push(Reverse((cost as u64, i, 1)));
+            }
+        }
+        while let Some(Reverse((dist, i, j))) = heap.pop() {
+            if dist > best[i][j] {
+                continue;
+            }
+            if self.grid.is_sink(i, j) {
+                return Some(dist);
+            }
+            for (r, c, cost) in self.adjacencies(i, j) {
+                let next = dist + cost as u64;
+                if next < best[r][c] {
+                    best[r][c] = next;
+                    heap.push(Reverse((next, r, c)));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn can_cross(&self, budget: u64) -> bool {
+        self.min_crossing_cost().is_some_and(|cost| cost <= budget)
+    }
+}
+
+impl From<&Grid> for CostGrid {
+    fn from(grid: &Grid) -> Self {
+        let mut result = Self::new_impassable(grid.rows, grid.cols);
+        for i in 1..=grid.rows {
+            for j in 1..=grid.cols {
+                if grid.cell(i, j) {
+                    result.set_cell(i, j, Some(1));
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Grid {
+    // Length (number of cells) of the shortest crossing path, if any
+    pub fn min_crossing_cost(&self) -> Option<u64> {
+        CostGrid::from(self).min_crossing_cost()
+    }
+}
+
+/*
+    Statistics of the minimum crossing cost
+*/
+#[derive(Clone, Debug, Default)]
+pub struct CostStats {
+    // Minimum crossing cost (None if impossible) -> weight of grids
+    pub histogram: BTreeMap<Option<u64>, u128>,
+    pub total: u128,
+}
+
+impl CostStats {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add(&mut self, cost: Option<u64>, weight: u128) {
+        *self.histogram.entry(cost).or_insert(0) += weight;
+        self.total += weight;
+    }
+
+    // Total weight of the grids where crossing is possible
+    pub fn crossable(&self) -> u128 {
+        self.total - self.histogram.get(&None).cloned().unwrap_or(0)
+    }
+
+    pub fn probability(&self, cost: Option<u64>) -> f64 {
+        self.histogram.get(&cost).cloned().unwrap_or(0) as f64
+            / self.total as f64
+    }
+
+    // Probability that the fish can cross with the given budget
+    pub fn probability_within(&self, budget: u64) -> f64 {
+        let weight: u128 = self
+            .histogram
+            .iter()
+            .filter(|(cost, _)| cost.is_some_and(|c| c <= budget))
+            .map(|(_, &w)| w)
+            .sum();
+        weight as f64 / self.total as f64
+    }
+
+    // Mean minimum crossing cost, over the grids where crossing is possible
+    pub fn mean_cost(&self) -> Option<f64> {
+        let mut weight = 0;
+        let mut weighted = 0.0;
+        for (cost, &w) in &self.histogram {
+            if let Some(c) = cost {
+                weight += w;
+                weighted += *c as f64 * w as f64;
+            }
+        }
+        if weight == 0 {
+            None
+        } else {
+            Some(weighted / weight as f64)
+        }
+    }
+}
+
+// Exact statistics over all grids, where each grid is weighted by the
+// product of the weights of its cells
+pub fn exhaustive_cost_stats<O: ProgressObserver + ?Sized>(
+    rows: usize,
+    cols: usize,
+    dist: &CostDistribution,
+    observer: &mut O,
+) -> CostStats {
+    let num_costs = dist.costs.len();
+    let expect_total = (num_costs as u128)
+        .checked_pow((rows * cols) as u32)
+        .expect("The number of grids is too large to fit in a u128");
+    // Check that the total weight fits
+    let _ = dist
+        .total_weight()
+        .checked_pow((rows * cols) as u32)
+        .expect("The total weight is too large to fit in a u128");
+    let mut tracker = Tracker::new(observer, expect_total);
+
+    // Index into dist for each cell (row by row)
+    let mut indices = vec![0; rows * cols];
+    let mut grid = CostGrid::new_impassable(rows, cols);
+    let mut stats = CostStats::new();
+    let mut done: u128 = 0;
+    loop {
+        let mut weight: u128 = 1;
+        for (k, &index) in indices.iter().enumerate() {
+            grid.set_cell(k / cols + 1, k % cols + 1, dist.costs[index]);
+            weight *= dist.weights[index] as u128;
+        }
+        if weight > 0 {
+            stats.add(grid.min_crossing_cost(), weight);
+        }
+        done += 1;
+        tracker.tick(done, stats.crossable());
+        // Next assignment (return false in case of overflow)
+        let more = indices.iter_mut().any(|index| {
+            *index = (*index + 1) % num_costs;
+            *index != 0
+        });
+        if !more {
+            break;
+        }
+    }
+    debug_assert_eq!(done, expect_total);
+    tracker.finish(done, stats.crossable());
+
+    stats
+}
+
+// Estimated statistics from random grids (each with weight 1)
+pub fn random_cost_stats<O: ProgressObserver + ?Sized>(
+    rows: usize,
+    cols: usize,
+    dist: &CostDistribution,
+    num_samples: usize,
+    observer: &mut O,
+) -> CostStats {
+    let mut tracker = Tracker::new(observer, num_samples as u128);
+    let mut stats = CostStats::new();
+    for sample in 0..num_samples {
+        let grid = CostGrid::new_random(rows, cols, dist);
+        stats.add(grid.min_crossing_cost(), 1);
+        tracker.tick(sample as u128 + 1, stats.crossable());
+    }
+    tracker.finish(num_samples as u128, stats.crossable());
+    stats
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Silent;
+
+    #[test]
+    fn test_distribution_text_format() {
+        let dist: CostDistribution = "1:2, 3:1,x:1".parse().unwrap();
+        assert_eq!(dist.costs, vec![Some(1), Some(3), None]);
+        assert_eq!(dist.weights, vec![2, 1, 1]);
+        assert_eq!(dist.total_weight(), 4);
+        assert_eq!(dist.to_string(), "1:2,3:1,x:1");
+        assert!("1".parse::<CostDistribution>().is_err());
+        assert!("y:1".parse::<CostDistribution>().is_err());
+        assert!("1:z".parse::<CostDistribution>().is_err());
+        assert!("1:0,x:0".parse::<CostDistribution>().is_err());
+    }
+
+    #[test]
+    fn test_min_crossing_cost() {
+        let mut grid = CostGrid::new_impassable(2, 3);
+        assert_eq!(grid.min_crossing_cost(), None);
+        // Top row: 5 5 5, bottom row: 1 9 1
+        for (j, cost) in [5, 5, 5].into_iter().enumerate() {
+            grid.set_cell(1, j + 1, Some(cost));
+        }
+        for (j, cost) in [1, 9, 1].into_iter().enumerate() {
+            grid.set_cell(2, j + 1, Some(cost));
+        }
+        // 1 + 5 + 5 + 1 beats 5 + 5 + 5 and 1 + 9 + 1
+        assert_eq!(grid.min_crossing_cost(), Some(11));
+        assert!(grid.can_cross(11));
+        assert!(!grid.can_cross(10));
+        grid.set_cell(1, 2, None);
+        assert_eq!(grid.min_crossing_cost(), Some(11));
+        grid.set_cell(2, 2, None);
+        assert_eq!(grid.min_crossing_cost(), None);
+    }
+
+    #[test]
+    fn test_grid_path_length() {
+        let grid: Grid = "BB.\n.BB\n".parse().unwrap();
+        assert_eq!(grid.min_crossing_cost(), Some(4));
+        let grid: Grid = "B.B\n...\n".parse().unwrap();
+        assert_eq!(grid.min_crossing_cost(), None);
+    }
+
+    #[test]
+    fn test_cost_stats() {
+        // 1 x 1 grids: cost 1 with weight 1, cost 2 with weight 2, or
+        // impassable with weight 1
+        let dist: CostDistribution = "1:1,2:2,x:1".parse().unwrap();
+        let stats = exhaustive_cost_stats(1, 1, &dist, &mut Silent);
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.crossable(), 3);
+        assert_eq!(stats.probability(Some(2)), 0.5);
+        assert_eq!(stats.probability(None), 0.25);
+        assert_eq!(stats.probability_within(1), 0.25);
+        assert_eq!(stats.probability_within(2), 0.75);
+        assert_eq!(stats.mean_cost(), Some(5.0 / 3.0));
+        // Zero weights are skipped
+        let dist: CostDistribution = "1:1,x:0".parse().unwrap();
+        let stats = exhaustive_cost_stats(2, 2, &dist, &mut Silent);
+        assert_eq!(stats.total, 1);
+        assert_eq!(stats.probability(Some(2)), 1.0);
+        assert_eq!(CostStats::new().mean_cost(), None);
+    }
+
+    #[test]
+    fn test_random_cost_stats() {
+        let dist: CostDistribution = "3:1".parse().unwrap();
+        let stats = random_cost_stats(2, 4, &dist, 10, &mut Silent);
+        assert_eq!(stats.total, 10);
+        assert_eq!(stats.probability(Some(12)), 1.0);
+    }
+}
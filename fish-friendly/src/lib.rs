@@ -2,10 +2,28 @@
     Problem abstractions
 */
 
+use partial::PartialGrid;
+use progress::{ProgressObserver, Silent, Tracker};
 use rand::Rng;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::hash::Hash;
 use std::iter;
+use std::str::FromStr;
+
+pub mod cluster;
+pub mod cnf;
+pub mod connected;
+pub mod constraints;
+pub mod energy;
+pub mod partial;
+pub mod progress;
+pub mod render;
+pub mod report;
+pub mod species;
+pub mod tides;
+pub mod transfer;
 
 /*
     Generic depth-first search
@@ -39,19 +57,213 @@ where
     false
 }
 
+// Same as dfs, but return the path found (from a source to a sink)
+pub fn dfs_path<T, Src, Succ, Succs, Snk>(
+    sources: Src,
+    get_succs: Succ,
+    is_sink: Snk,
+) -> Option<Vec<T>>
+where
+    T: Clone + Hash + Eq,
+    Src: Iterator<Item = T>,
+    Succ: Fn(&T) -> Succs,
+    Succs: Iterator<Item = T>,
+    Snk: Fn(&T) -> bool,
+{
+    // Visited nodes, and the node we came from
+    let mut parents: HashMap<T, Option<T>> = HashMap::new();
+    let mut to_visit: Vec<(T, Option<T>)> =
+        sources.map(|s| (s, None)).collect();
+    while let Some((curr, parent)) = to_visit.pop() {
+        if parents.contains_key(&curr) {
+            continue;
+        }
+        parents.insert(curr.clone(), parent);
+        if is_sink(&curr) {
+            let mut path = vec![curr];
+            while let Some(Some(prev)) = parents.get(path.last().unwrap()) {
+                path.push(prev.clone());
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for next in get_succs(&curr) {
+            to_visit.push((next, Some(curr.clone())));
+        }
+    }
+    None
+}
+
+/*
+    Move rule
+*/
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MoveRule {
+    // Up, down, left, and right (the original problem)
+    AllSteps,
+    // Up, down, and right only: no right-to-left ("backwards") steps
+    NoBackwardSteps,
+}
+
+impl MoveRule {
+    pub fn backwards(self) -> bool {
+        self == MoveRule::AllSteps
+    }
+}
+
+// note: change this to MoveRule::NoBackwardSteps to prevent "backwards" steps
+pub const MOVE_RULE: MoveRule = MoveRule::AllSteps;
+
+/*
+    Algorithm used to check whether the fish can get across
+*/
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Algorithm {
+    // Depth-first search from the first column (Grid::fish_friendly)
+    Dfs,
+    // Union-find over the whole grid (Grid::fish_friendly_union_find)
+    UnionFind,
+}
+
+impl Algorithm {
+    // Whether the algorithm applies under MOVE_RULE
+    pub fn supported(self) -> bool {
+        match self {
+            Algorithm::Dfs => true,
+            Algorithm::UnionFind => MOVE_RULE.backwards(),
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dfs" => Ok(Algorithm::Dfs),
+            "union-find" => Ok(Algorithm::UnionFind),
+            _ => Err(format!("Unknown algorithm {:?} (dfs or union-find)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Algorithm::Dfs => "dfs",
+            Algorithm::UnionFind => "union-find",
+        })
+    }
+}
+
 /*
     Grid struct
+
+    The cells are indexed from 1, with a border of cells around the grid
+    that the fish can't swim through. The usual grid has bool cells (blue or
+    not); other cell types (see species.rs and energy.rs) share the logic
+    for the border, the move rule, and iterating over all grids.
 */
-pub struct Grid {
+#[derive(Clone)]
+pub struct Grid<C = bool> {
     rows: usize,
     cols: usize,
-    grid: Vec<Vec<bool>>,
+    grid: Vec<Vec<C>>,
+}
+
+impl<C: Copy + Default + PartialEq> Grid<C> {
+    // All cells are C::default(), and the border is the given cell
+    pub(crate) fn new_with_border(rows: usize, cols: usize, border: C) -> Self {
+        let mut grid = vec![vec![border; cols + 2]; rows + 2];
+        for row in grid.iter_mut().take(rows + 1).skip(1) {
+            for cell in row.iter_mut().take(cols + 1).skip(1) {
+                *cell = C::default();
+            }
+        }
+        Self { rows, cols, grid }
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn get_cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn is_sink(&self, _i: usize, j: usize) -> bool {
+        j == self.cols
+    }
+
+    // Cells in the first column that the fish can swim through
+    pub(crate) fn sources_where<'a, P: Fn(C) -> bool + 'a>(
+        &'a self,
+        swim: P,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        (1..=self.rows)
+            .map(|i| (i, 1))
+            .filter(move |&(i, j)| swim(self.grid[i][j]))
+    }
+
+    // Cells the fish can move to from (i, j), following MOVE_RULE. The
+    // border cells should be ones the fish can't swim through.
+    pub(crate) fn adjacencies_where<'a, P: Fn(C) -> bool + 'a>(
+        &'a self,
+        i: usize,
+        j: usize,
+        swim: P,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let backwards = Some((i, j - 1)).filter(|_| MOVE_RULE.backwards());
+        iter::once((i - 1, j))
+            .chain(iter::once((i + 1, j)))
+            .chain(backwards)
+            .chain(iter::once((i, j + 1)))
+            .filter(move |&(r, c)| swim(self.grid[r][c]))
+    }
+
+    // Methods to iterate over all grids, where next gives the cell after
+    // each cell, wrapping around to C::default()
+    // return false in case of overflow
+
+    fn rotate_cell_with<F: Fn(C) -> C>(
+        &mut self,
+        i: usize,
+        j: usize,
+        next: &F,
+    ) -> bool {
+        self.grid[i][j] = next(self.grid[i][j]);
+        self.grid[i][j] != C::default()
+    }
+
+    pub(crate) fn rotate_with<F: Fn(C) -> C>(&mut self, next: F) -> bool {
+        let cols = self.cols;
+        (1..=self.rows)
+            .any(|i| (1..=cols).any(|j| self.rotate_cell_with(i, j, &next)))
+    }
+
+    // Same, but only rotate the given cells (leaving the others fixed)
+    pub(crate) fn rotate_cells_with<F: Fn(C) -> C>(
+        &mut self,
+        cells: &[(usize, usize)],
+        next: F,
+    ) -> bool {
+        cells.iter().any(|&(i, j)| self.rotate_cell_with(i, j, &next))
+    }
 }
 
 impl Grid {
-    #[allow(clippy::needless_range_loop)]
     pub fn new_random(rows: usize, cols: usize) -> Self {
-        let mut rng = rand::thread_rng();
+        Self::new_random_with(rows, cols, &mut rand::thread_rng())
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn new_random_with<R: Rng>(
+        rows: usize,
+        cols: usize,
+        rng: &mut R,
+    ) -> Self {
         let mut grid = vec![vec![false; cols + 2]; rows + 2];
         for row in 1..=rows {
             for col in 1..=cols {
@@ -62,20 +274,32 @@ impl Grid {
     }
 
     pub fn new_empty(rows: usize, cols: usize) -> Self {
-        let grid = vec![vec![false; cols + 2]; rows + 2];
-        Self { rows, cols, grid }
+        Self::new_with_border(rows, cols, false)
     }
 
     pub fn cell(&self, i: usize, j: usize) -> bool {
         self.grid[i][j]
     }
 
+    pub fn set_cell(&mut self, i: usize, j: usize, blue: bool) {
+        assert!((1..=self.rows).contains(&i) && (1..=self.cols).contains(&j));
+        self.grid[i][j] = blue;
+    }
+
     pub fn sources(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
-        (1..=self.rows).map(|i| (i, 1)).filter(|&(i, j)| self.cell(i, j))
+        self.sources_where(|blue| blue)
     }
 
-    pub fn is_sink(&self, _i: usize, j: usize) -> bool {
-        j == self.cols
+    // Blue cells adjacent to (i, j) in any direction
+    // (unlike adjacencies, this does not depend on which steps are allowed)
+    pub fn neighbors(
+        &self,
+        i: usize,
+        j: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        [(i - 1, j), (i + 1, j), (i, j - 1), (i, j + 1)]
+            .into_iter()
+            .filter(|&(r, c)| self.cell(r, c))
     }
 
     pub fn adjacencies(
@@ -83,11 +307,7 @@ impl Grid {
         i: usize,
         j: usize,
     ) -> impl Iterator<Item = (usize, usize)> + '_ {
-        iter::once((i - 1, j))
-            .chain(iter::once((i + 1, j)))
-            .chain(iter::once((i, j - 1))) // note: remove this line to prevent "backwards" steps
-            .chain(iter::once((i, j + 1)))
-            .filter(|&(r, c)| self.cell(r, c))
+        self.adjacencies_where(i, j, |blue| blue)
     }
 
     // Check if the fish can get across swimming only on 'true' cells
@@ -99,30 +319,156 @@ impl Grid {
         )
     }
 
+    // Same, but by scanning the grid once and joining adjacent blue cells
+    // with union-find, with virtual nodes for the left and right edges.
+    // This avoids the HashSet used by dfs. Connectivity is undirected, so
+    // this only applies when backwards steps are allowed (see
+    // Algorithm::supported).
+    pub fn fish_friendly_union_find(&self) -> bool {
+        assert!(
+            Algorithm::UnionFind.supported(),
+            "Union-find assumes backwards steps are allowed"
+        );
+        let (rows, cols) = (self.rows, self.cols);
+        let left = rows * cols;
+        let right = left + 1;
+        let mut parent: Vec<usize> = (0..(right + 1)).collect();
+        let index = |i: usize, j: usize| (i - 1) * cols + (j - 1);
+        for i in 1..=rows {
+            for j in 1..=cols {
+                if !self.cell(i, j) {
+                    continue;
+                }
+                if j == 1 {
+                    transfer::union(&mut parent, index(i, j), left);
+                }
+                if j == cols {
+                    transfer::union(&mut parent, index(i, j), right);
+                }
+                if self.cell(i + 1, j) {
+                    transfer::union(&mut parent, index(i, j), index(i + 1, j));
+                }
+                if self.cell(i, j + 1) {
+                    transfer::union(&mut parent, index(i, j), index(i, j + 1));
+                }
+            }
+        }
+        transfer::find(&mut parent, left) == transfer::find(&mut parent, right)
+    }
+
+    // Check if the fish can get across, with the given algorithm
+    pub fn fish_friendly_using(&self, algorithm: Algorithm) -> bool {
+        match algorithm {
+            Algorithm::Dfs => self.fish_friendly(),
+            Algorithm::UnionFind => self.fish_friendly_union_find(),
+        }
+    }
+
+    // A path the fish can take across, if any
+    pub fn fish_path(&self) -> Option<Vec<(usize, usize)>> {
+        dfs_path(
+            self.sources(),
+            |&(i, j)| self.adjacencies(i, j),
+            |&(i, j)| self.is_sink(i, j),
+        )
+    }
+
+    // If the fish can't get across, a chain of water cells blocking it:
+    // from the top row to the bottom row, where consecutive cells are
+    // adjacent horizontally, vertically, or diagonally.
+    // (Exactly one of fish_path and blocking_cut exists when backwards
+    // steps are allowed. Without them, the fish can be blocked even when
+    // there is no such chain, and then neither exists.)
+    pub fn blocking_cut(&self) -> Option<Vec<(usize, usize)>> {
+        let is_water = |&(i, j): &(usize, usize)| {
+            (1..=self.rows).contains(&i)
+                && (1..=self.cols).contains(&j)
+                && !self.cell(i, j)
+        };
+        dfs_path(
+            (1..=self.cols).map(|j| (1, j)).filter(is_water),
+            |&(i, j)| {
+                (i - 1..=i + 1)
+                    .flat_map(move |r| (j - 1..=j + 1).map(move |c| (r, c)))
+                    .filter(is_water)
+            },
+            |&(i, _)| i == self.rows,
+        )
+    }
+
+    // Label the blue cells by connected component, numbered 0, 1, ... in
+    // order of their first cell (row by row); water cells are None.
+    // Returns the labels (indexed like the grid) and the number of components.
+    pub fn components(&self) -> (Vec<Vec<Option<usize>>>, usize) {
+        let mut labels = vec![vec![None; self.cols + 2]; self.rows + 2];
+        let mut num_components = 0;
+        for i in 1..=self.rows {
+            for j in 1..=self.cols {
+                if !self.cell(i, j) || labels[i][j].is_some() {
+                    continue;
+                }
+                let mut to_visit = vec![(i, j)];
+                while let Some((r, c)) = to_visit.pop() {
+                    if labels[r][c].is_none() {
+                        labels[r][c] = Some(num_components);
+                        to_visit.extend(self.neighbors(r, c));
+                    }
+                }
+                num_components += 1;
+            }
+        }
+        (labels, num_components)
+    }
+
     // Methods to iterate over all grids
     // return false in case of overflow
 
-    fn rotate_cell(&mut self, i: usize, j: usize) -> bool {
-        self.grid[i][j] = !self.grid[i][j];
-        self.grid[i][j]
+    pub fn rotate(&mut self) -> bool {
+        self.rotate_with(|blue: bool| !blue)
     }
 
-    fn rotate_row(&mut self, i: usize) -> bool {
-        (1..=self.cols).any(|j| self.rotate_cell(i, j))
+    // Same, but only rotate the given cells (leaving the others fixed)
+    pub fn rotate_cells(&mut self, cells: &[(usize, usize)]) -> bool {
+        self.rotate_cells_with(cells, |blue: bool| !blue)
     }
+}
 
-    pub fn rotate(&mut self) -> bool {
-        (1..=self.rows).any(|i| self.rotate_row(i))
+// Text format: one line per row, B for blue and . for water
+// (see partial.rs)
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", PartialGrid::from(self))
     }
 }
 
-const PROGRESS_STEPS: u128 = 10;
+impl FromStr for Grid {
+    type Err = String;
 
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let partial: PartialGrid = s.parse()?;
+        partial
+            .to_grid()
+            .ok_or_else(|| "Grid should not have free cells".to_string())
+    }
+}
+
+// Count friendly grids, without reporting progress
+// Returns (friendly, total)
 pub fn count_friendly_grids(rows: usize, cols: usize) -> (u128, u128) {
+    count_friendly_grids_with(rows, cols, &mut Silent)
+}
+
+// Count friendly grids, reporting progress to the observer
+// Returns (friendly, total)
+pub fn count_friendly_grids_with<O: ProgressObserver + ?Sized>(
+    rows: usize,
+    cols: usize,
+    observer: &mut O,
+) -> (u128, u128) {
     let expect_total: u128 = 2_u128
         .checked_pow((rows * cols) as u32)
         .expect("The number of grids is too large to fit in a u128");
-    let progress_step = expect_total / PROGRESS_STEPS;
+    let mut tracker = Tracker::new(observer, expect_total);
 
     let mut total: u128 = 0;
     let mut friendly: u128 = 0;
@@ -132,17 +478,417 @@ pub fn count_friendly_grids(rows: usize, cols: usize) -> (u128, u128) {
             friendly += 1;
         }
         total += 1;
-        if progress_step > 0 && total % progress_step == 0 {
-            println!(
-                "Progress: {:.0}%",
-                (total as f64) * 100.0 / (expect_total as f64)
-            );
-        }
+        tracker.tick(total, friendly);
         if !grid.rotate() {
             break;
         }
     }
     debug_assert_eq!(total, expect_total);
+    tracker.finish(total, friendly);
 
     (friendly, total)
 }
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // Compare strategies against brute force for all rows * cols up to this
+    const BRUTE_FORCE_UPTO: usize = 16;
+    const TRANSFER_UPTO: usize = 8;
+    const NUM_RANDOM_GRIDS: usize = 2000;
+
+    // Friendly table from results.md (original problem), indexed by
+    // [rows - 1][cols - 1]
+    const RESULTS_TABLE: [&[u128]; 10] = [
+        &[1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+        &[3, 7, 17, 41, 99, 239, 577, 1393, 3363],
+        &[7, 37, 197, 1041, 5503, 29089, 153769, 812849],
+        &[15, 175, 1985, 22193, 247759, 2764991, 30856705],
+        &[31, 781, 18621, 433809, 10056959, 232824241],
+        &[63, 3367, 167337, 8057905, 384479935],
+        &[127, 14197, 1461797, 144769425],
+        &[255, 58975, 12519345],
+        &[511, 242461],
+        &[1023],
+    ];
+
+    fn small_sizes() -> impl Iterator<Item = (usize, usize)> {
+        (1..=BRUTE_FORCE_UPTO).flat_map(|rows| {
+            (1..=(BRUTE_FORCE_UPTO / rows)).map(move |cols| (rows, cols))
+        })
+    }
+
+    fn brute_force(rows: usize, cols: usize) -> u128 {
+        count_friendly_grids(rows, cols).0
+    }
+
+    #[test]
+    fn test_golden_brute_force() {
+        for (rows, cols) in small_sizes() {
+            let row = RESULTS_TABLE.get(rows - 1);
+            if let Some(&expected) = row.and_then(|row| row.get(cols - 1)) {
+                assert_eq!(
+                    brute_force(rows, cols),
+                    expected,
+                    "{} x {}",
+                    rows,
+                    cols
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_golden_transfer_matrix() {
+        for (r, row) in RESULTS_TABLE.iter().enumerate().take(TRANSFER_UPTO) {
+            let matrix = transfer::TransferMatrix::new(r + 1);
+            for (c, &expected) in row.iter().enumerate() {
+                assert_eq!(matrix.count_friendly(c + 1), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_total() {
+        for (rows, cols) in small_sizes().filter(|&(r, c)| r * c <= 10) {
+            assert_eq!(count_friendly_grids(rows, cols).1, 1 << (rows * cols));
+        }
+    }
+
+    #[test]
+    fn test_algorithm_from_str() {
+        for algorithm in [Algorithm::Dfs, Algorithm::UnionFind] {
+            assert_eq!(algorithm.to_string().parse(), Ok(algorithm));
+        }
+        assert_eq!(format!("{:>5}", Algorithm::Dfs), "  dfs");
+        assert!("bfs".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn test_union_find_random() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..NUM_RANDOM_GRIDS {
+            let rows = rng.gen_range(1..=12);
+            let cols = rng.gen_range(1..=12);
+            let grid = Grid::new_random_with(rows, cols, &mut rng);
+            assert_eq!(
+                grid.fish_friendly_using(Algorithm::UnionFind),
+                grid.fish_friendly(),
+                "\n{}",
+                grid
+            );
+        }
+        // Every grid, against the table
+        for (rows, cols) in small_sizes().filter(|&(r, c)| r * c <= 12) {
+            let mut grid = Grid::new_empty(rows, cols);
+            let mut friendly = 0;
+            loop {
+                friendly += grid.fish_friendly_union_find() as u128;
+                if !grid.rotate() {
+                    break;
+                }
+            }
+            assert_eq!(friendly, brute_force(rows, cols));
+        }
+    }
+
+    #[test]
+    fn test_path_and_cut() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..NUM_RANDOM_GRIDS {
+            let rows = rng.gen_range(1..=12);
+            let cols = rng.gen_range(1..=12);
+            let grid = Grid::new_random_with(rows, cols, &mut rng);
+            let path = grid.fish_path();
+            assert_eq!(path.is_some(), grid.fish_friendly());
+            assert_eq!(grid.blocking_cut().is_some(), !grid.fish_friendly());
+            if let Some(path) = path {
+                assert_eq!(path[0].1, 1);
+                assert_eq!(path[path.len() - 1].1, cols);
+                assert!(path.iter().all(|&(i, j)| grid.cell(i, j)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_text_format() {
+        let text = "BB.\n.BB\n";
+        let grid: Grid = text.parse().unwrap();
+        assert!(grid.fish_friendly());
+        assert_eq!(grid.to_string(), text);
+        assert!("B?B\n".parse::<Grid>().is_err());
+        assert!("B.\nB\n".parse::<Grid>().is_err());
+    }
+
+    #[test]
+    fn test_transfer_vs_brute_force() {
+        // The matrix has all states for its number of rows, regardless of
+        // the number of columns, so only check up to TRANSFER_UPTO rows
+        for (rows, cols) in small_sizes().filter(|&(r, _)| r <= TRANSFER_UPTO) {
+            let matrix = transfer::TransferMatrix::new(rows);
+            assert_eq!(matrix.count_friendly(cols), brute_force(rows, cols));
+        }
+    }
+
+    #[test]
+    fn test_partial_vs_brute_force() {
+        for (rows, cols) in small_sizes() {
+            let free = partial::PartialGrid::new_free(rows, cols);
+            let expected = (brute_force(rows, cols), 1 << (rows * cols));
+            assert_eq!(free.count_completions_dp(), expected);
+        }
+        // Partially fixed grids
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..200 {
+            let rows = rng.gen_range(1..=4);
+            let cols = rng.gen_range(1..=4);
+            let mut grid = partial::PartialGrid::new_free(rows, cols);
+            for i in 0..rows {
+                for j in 0..cols {
+                    let cell = match rng.gen_range(0..3) {
+                        0 => partial::Cell::Blue,
+                        1 => partial::Cell::Water,
+                        _ => partial::Cell::Free,
+                    };
+                    grid.set_cell(i, j, cell);
+                }
+            }
+            assert_eq!(
+                grid.count_completions_dp(),
+                grid.count_completions_brute()
+            );
+        }
+    }
+
+    #[test]
+    fn test_cnf_vs_brute_force() {
+        // count_models finds the models one at a time, each with a full
+        // round of unit propagation: 12 cells take a few seconds, but 16
+        // cells take about two minutes in a debug build, so the larger sizes
+        // are in test_cnf_vs_brute_force_large
+        for (rows, cols) in small_sizes().filter(|&(r, c)| r * c <= 12) {
+            check_cnf(rows, cols);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_cnf_vs_brute_force_large() {
+        for (rows, cols) in small_sizes().filter(|&(r, c)| r * c > 12) {
+            check_cnf(rows, cols);
+        }
+    }
+
+    fn check_cnf(rows: usize, cols: usize) {
+        let friendly = brute_force(rows, cols);
+        let total = 1 << (rows * cols);
+        assert_eq!(cnf::encode(rows, cols, true).count_models(), friendly);
+        assert_eq!(
+            cnf::encode(rows, cols, false).count_models(),
+            total - friendly
+        );
+    }
+
+    #[test]
+    fn test_species_vs_brute_force() {
+        // One species and two colors is the original problem
+        let (colors, masks) = species::standard_species(1, 0);
+        for (rows, cols) in small_sizes() {
+            let result =
+                species::count_multi_species(rows, cols, colors, &masks);
+            assert_eq!(result.all, brute_force(rows, cols));
+            assert_eq!(result.any, brute_force(rows, cols));
+        }
+    }
+
+    #[test]
+    fn test_energy_vs_brute_force() {
+        let dist: energy::CostDistribution = "1:1,x:1".parse().unwrap();
+        for (rows, cols) in small_sizes() {
+            let stats =
+                energy::exhaustive_cost_stats(rows, cols, &dist, &mut Silent);
+            assert_eq!(stats.crossable(), brute_force(rows, cols));
+            // The shortest crossing path has at least cols cells
+            assert!(stats
+                .histogram
+                .keys()
+                .flatten()
+                .all(|&c| c >= cols as u64));
+        }
+    }
+
+    #[test]
+    fn test_constraints_unconstrained() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let none = constraints::Constraints::default();
+        for _ in 0..NUM_RANDOM_GRIDS {
+            let rows = rng.gen_range(1..=8);
+            let cols = rng.gen_range(1..=8);
+            let grid = Grid::new_random_with(rows, cols, &mut rng);
+            assert_eq!(grid.fish_friendly_with(&none), grid.fish_friendly());
+        }
+    }
+
+    #[test]
+    fn test_constraints_max_length() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..NUM_RANDOM_GRIDS {
+            let rows = rng.gen_range(1..=6);
+            let cols = rng.gen_range(1..=6);
+            let grid = Grid::new_random_with(rows, cols, &mut rng);
+            let max_length = rng.gen_range(1..=(2 * cols));
+            let c = constraints::Constraints {
+                max_length: Some(max_length),
+                ..Default::default()
+            };
+            let expected = grid
+                .min_crossing_cost()
+                .is_some_and(|l| l <= max_length as u64);
+            assert_eq!(grid.fish_friendly_with(&c), expected, "\n{}", grid);
+            if let Some(path) = grid.fish_path_with(&c) {
+                assert!(path.len() <= max_length);
+            }
+        }
+    }
+
+    #[test]
+    fn test_constraints_waypoint() {
+        // Since the fish may revisit cells, it can pass through a waypoint
+        // iff the waypoint's component touches both sides
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..NUM_RANDOM_GRIDS {
+            let rows = rng.gen_range(1..=8);
+            let cols = rng.gen_range(1..=8);
+            let grid = Grid::new_random_with(rows, cols, &mut rng);
+            let waypoint = (rng.gen_range(1..=rows), rng.gen_range(1..=cols));
+            let c = constraints::Constraints {
+                waypoints: vec![waypoint],
+                ..Default::default()
+            };
+            let (labels, _) = grid.components();
+            let label = labels[waypoint.0][waypoint.1];
+            let expected = label.is_some()
+                && (1..=rows).any(|i| labels[i][1] == label)
+                && (1..=rows).any(|i| labels[i][cols] == label);
+            assert_eq!(grid.fish_friendly_with(&c), expected, "\n{}", grid);
+            if let Some(path) = grid.fish_path_with(&c) {
+                assert!(path.contains(&waypoint));
+            }
+        }
+    }
+
+    #[test]
+    fn test_constraints_no_left_turns() {
+        // The only path turns right, then left
+        let grid: Grid = "BB.\n.B.\n.BB\n".parse().unwrap();
+        let c = constraints::Constraints {
+            no_left_turns: true,
+            ..Default::default()
+        };
+        assert!(grid.fish_friendly());
+        assert!(!grid.fish_friendly_with(&c));
+        // Paths found only turn right
+        let mut rng = StdRng::seed_from_u64(6);
+        for _ in 0..NUM_RANDOM_GRIDS {
+            let rows = rng.gen_range(1..=8);
+            let cols = rng.gen_range(1..=8);
+            let grid = Grid::new_random_with(rows, cols, &mut rng);
+            let Some(path) = grid.fish_path_with(&c) else {
+                continue;
+            };
+            let steps: Vec<(isize, isize)> = iter::once((0, 1))
+                .chain(path.windows(2).map(|w| {
+                    let (a, b) = (w[0], w[1]);
+                    (b.0 as isize - a.0 as isize, b.1 as isize - a.1 as isize)
+                }))
+                .collect();
+            for w in steps.windows(2) {
+                // Cross product of consecutive steps (rows point down)
+                let ((di, dj), (ei, ej)) = (w[0], w[1]);
+                assert!(w[0] == w[1] || di * ej - dj * ei < 0, "{:?}", path);
+            }
+        }
+        // Counts only go down
+        let all = vec![
+            constraints::Constraints::default(),
+            c.clone(),
+            constraints::Constraints {
+                waypoints: vec![(1, 1)],
+                no_left_turns: true,
+                max_length: Some(4),
+            },
+        ];
+        for (rows, cols) in small_sizes().filter(|&(r, c)| r * c <= 9) {
+            let (friendly, total) =
+                constraints::count_constrained_grids(rows, cols, &all);
+            assert_eq!(friendly[0], brute_force(rows, cols));
+            assert_eq!(total, 1 << (rows * cols));
+            assert!(friendly[0] >= friendly[1] && friendly[1] >= friendly[2]);
+        }
+    }
+
+    #[test]
+    fn test_connected_dp_vs_brute_force() {
+        assert_eq!(connected::count_connected_dp(3, 3).connected, 218);
+        for (rows, cols) in small_sizes().filter(|&(r, c)| r * c <= 12) {
+            let expected = connected::count_connected_grids(rows, cols);
+            assert_eq!(connected::count_connected_dp(rows, cols), expected);
+        }
+    }
+
+    #[test]
+    fn test_count_result_json() {
+        use report::CountMethod;
+        for method in [CountMethod::BruteForce, CountMethod::TransferMatrix] {
+            let result =
+                report::CountResult::count(3, 4, method, &mut Silent).unwrap();
+            assert_eq!((result.friendly, result.total), (1041, 4096));
+            let json = result.to_json();
+            assert!(json.contains("\"friendly\":1041"));
+            let parsed: report::CountResult =
+                serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.method, method.into());
+            let expected = match method {
+                CountMethod::BruteForce => Some(Algorithm::Dfs),
+                CountMethod::TransferMatrix => None,
+            };
+            assert_eq!(parsed.algorithm, expected);
+            assert_eq!(parsed.move_rule, MOVE_RULE);
+            assert_eq!((parsed.friendly, parsed.total), (1041, 4096));
+        }
+    }
+
+    #[test]
+    fn test_count_result_errors() {
+        use report::{CountMethod, CountResult, Method};
+        let max = transfer::MAX_ROWS;
+        // Too few or too many rows, or too many grids to count in a u128
+        for (rows, cols, method) in [
+            (0, 2, CountMethod::TransferMatrix),
+            (max + 1, 2, CountMethod::TransferMatrix),
+            (12, 12, CountMethod::BruteForce),
+            (1, 200, CountMethod::TransferMatrix),
+        ] {
+            let result = CountResult::count(rows, cols, method, &mut Silent);
+            assert!(result.is_err());
+        }
+        // A 1 x cols grid is friendly only if all blue
+        let result =
+            CountResult::count(1, 5, CountMethod::TransferMatrix, &mut Silent);
+        assert_eq!(result.unwrap().friendly, 1);
+        // Random sampling parses as a Method, but not as a CountMethod
+        let method: Method = "random-sampling".parse().unwrap();
+        assert_eq!(method, Method::RandomSampling);
+        assert!(CountMethod::try_from(method).is_err());
+        assert!("random-sampling".parse::<CountMethod>().is_err());
+        assert!("brute".parse::<CountMethod>().is_err());
+        let method: CountMethod = "transfer-matrix".parse().unwrap();
+        assert_eq!(method, CountMethod::TransferMatrix);
+    }
+}
@@ -0,0 +1,308 @@
+/*
+    Partially specified grids
+
+    Some cells are fixed as blue or water, and the rest are free.
+    We count the number of ways to fill in the free cells so that the
+    resulting grid is fish-friendly.
+
+    Text format: one line per row, with
+        B: blue
+        .: water
+        ?: free
+    (This is also the text format for Grid, without free cells.)
+*/
+
+use super::transfer::{self, State};
+use super::{Grid, MOVE_RULE};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Cell {
+    Blue,
+    Water,
+    Free,
+}
+
+impl Cell {
+    pub fn to_char(self) -> char {
+        match self {
+            Cell::Blue => 'B',
+            Cell::Water => '.',
+            Cell::Free => '?',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'B' => Some(Cell::Blue),
+            '.' => Some(Cell::Water),
+            '?' => Some(Cell::Free),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PartialGrid {
+    rows: usize,
+    cols: usize,
+    // Unlike Grid, this is 0-indexed and has no border
+    cells: Vec<Vec<Cell>>,
+}
+
+impl PartialGrid {
+    pub fn new_free(rows: usize, cols: usize) -> Self {
+        Self { rows, cols, cells: vec![vec![Cell::Free; cols]; rows] }
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn get_cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn cell(&self, i: usize, j: usize) -> Cell {
+        self.cells[i][j]
+    }
+
+    pub fn set_cell(&mut self, i: usize, j: usize, cell: Cell) {
+        self.cells[i][j] = cell;
+    }
+
+    pub fn num_free(&self) -> usize {
+        self.cells.iter().flatten().filter(|&&c| c == Cell::Free).count()
+    }
+
+    // Total number of completions
+    pub fn num_completions(&self) -> u128 {
+        2_u128
+            .checked_pow(self.num_free() as u32)
+            .expect("The number of completions is too large to fit in a u128")
+    }
+
+    // The corresponding grid, if there are no free cells
+    pub fn to_grid(&self) -> Option<Grid> {
+        if self.num_free() == 0 {
+            Some(self.to_grid_with_water())
+        } else {
+            None
+        }
+    }
+
+    // The grid with all free cells set to water
+    fn to_grid_with_water(&self) -> Grid {
+        let mut grid = Grid::new_empty(self.rows, self.cols);
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                grid.grid[i + 1][j + 1] = self.cells[i][j] == Cell::Blue;
+            }
+        }
+        grid
+    }
+
+    // Count friendly completions by enumerating all of them
+    // Returns (friendly, total)
+    pub fn count_completions_brute(&self) -> (u128, u128) {
+        let expect_total = self.num_completions();
+        let free: Vec<(usize, usize)> = (0..self.rows)
+            .flat_map(|i| (0..self.cols).map(move |j| (i, j)))
+            .filter(|&(i, j)| self.cells[i][j] == Cell::Free)
+            .map(|(i, j)| (i + 1, j + 1))
+            .collect();
+
+        let mut total: u128 = 0;
+        let mut friendly: u128 = 0;
+        let mut grid = self.to_grid_with_water();
+        loop {
+            if grid.fish_friendly() {
+                friendly += 1;
+            }
+            total += 1;
+            if !grid.rotate_cells(&free) {
+                break;
+            }
+        }
+        debug_assert_eq!(total, expect_total);
+
+        (friendly, total)
+    }
+
+    // Count friendly completions column by column, using the transfer matrix
+    // states (see transfer.rs). This is exponential in the number of rows
+    // but only linear in the number of columns. Like the transfer matrix,
+    // this needs MOVE_RULE to allow backwards steps.
+    // Returns (friendly, total)
+    pub fn count_completions_dp(&self) -> (u128, u128) {
+        assert!(
+            MOVE_RULE.backwards(),
+            "The column-by-column count assumes backwards steps are allowed"
+        );
+        assert!(self.rows < 32, "Too many rows for the column-by-column count");
+        let total = self.num_completions();
+        if self.cols == 0 {
+            return (0, total);
+        }
+        // The left edge acts like a column that is all blue and
+        // connected to the left edge
+        let mut counts: HashMap<State, u128> = HashMap::new();
+        counts.insert(vec![1; self.rows], 1);
+        for j in 0..self.cols {
+            let fixed: u32 = (0..self.rows)
+                .filter(|&i| self.cells[i][j] == Cell::Blue)
+                .map(|i| 1 << i)
+                .sum();
+            let free: Vec<usize> = (0..self.rows)
+                .filter(|&i| self.cells[i][j] == Cell::Free)
+                .collect();
+            let mut next: HashMap<State, u128> = HashMap::new();
+            for bits in 0..(1_u32 << free.len()) {
+                let column = free
+                    .iter()
+                    .enumerate()
+                    .filter(|&(k, _)| bits & (1 << k) != 0)
+                    .map(|(_, &i)| 1 << i)
+                    .fold(fixed, |acc, b| acc | b);
+                for (state, &count) in &counts {
+                    if let Some(succ) = transfer::step(state, column) {
+                        *next.entry(succ).or_insert(0) += count;
+                    }
+                }
+            }
+            counts = next;
+        }
+        (counts.values().sum(), total)
+    }
+
+    // Count friendly completions, using the faster method when possible
+    // Returns (friendly, total)
+    pub fn count_completions(&self) -> (u128, u128) {
+        if self.rows <= transfer::MAX_ROWS && MOVE_RULE.backwards() {
+            self.count_completions_dp()
+        } else {
+            self.count_completions_brute()
+        }
+    }
+
+    // Probability that a uniformly random completion is friendly
+    pub fn friendly_probability(&self) -> f64 {
+        let (friendly, total) = self.count_completions();
+        (friendly as f64) / (total as f64)
+    }
+}
+
+impl From<&Grid> for PartialGrid {
+    fn from(grid: &Grid) -> Self {
+        let mut result = Self::new_free(grid.rows, grid.cols);
+        for i in 0..grid.rows {
+            for j in 0..grid.cols {
+                result.cells[i][j] = if grid.cell(i + 1, j + 1) {
+                    Cell::Blue
+                } else {
+                    Cell::Water
+                };
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Display for PartialGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.cells {
+            for &cell in row {
+                write!(f, "{}", cell.to_char())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PartialGrid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cells = Vec::new();
+        for (i, line) in s.lines().map(str::trim).enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let row = line
+                .chars()
+                .map(|c| {
+                    Cell::from_char(c).ok_or_else(|| {
+                        format!("Unexpected character {:?} in row {}", c, i + 1)
+                    })
+                })
+                .collect::<Result<Vec<Cell>, String>>()?;
+            cells.push(row);
+        }
+        let rows = cells.len();
+        let cols = cells.first().map_or(0, |row| row.len());
+        if cells.iter().any(|row| row.len() != cols) {
+            return Err("All rows should have the same length".to_string());
+        }
+        Ok(Self { rows, cols, cells })
+    }
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_format() {
+        let text = "B?.\n?.B\n";
+        let grid: PartialGrid = text.parse().unwrap();
+        assert_eq!((grid.get_rows(), grid.get_cols()), (2, 3));
+        assert_eq!(grid.cell(0, 1), Cell::Free);
+        assert_eq!(grid.cell(1, 2), Cell::Blue);
+        assert_eq!(grid.to_string(), text);
+        assert!("B?x\n".parse::<PartialGrid>().is_err());
+        assert!("B?\n?\n".parse::<PartialGrid>().is_err());
+    }
+
+    #[test]
+    fn test_to_and_from_grid() {
+        let grid: Grid = "B.\n.B\n".parse().unwrap();
+        let partial = PartialGrid::from(&grid);
+        assert_eq!(partial.num_free(), 0);
+        assert_eq!(partial.num_completions(), 1);
+        assert_eq!(partial.to_grid().unwrap().to_string(), grid.to_string());
+        let mut partial = partial;
+        partial.set_cell(0, 1, Cell::Free);
+        assert_eq!(partial.num_free(), 1);
+        assert_eq!(partial.num_completions(), 2);
+        assert!(partial.to_grid().is_none());
+    }
+
+    #[test]
+    fn test_fixed_cells() {
+        // A water column blocks every completion
+        let grid: PartialGrid = "?.?\n?.?\n".parse().unwrap();
+        assert_eq!(grid.count_completions_dp(), (0, 16));
+        assert_eq!(grid.count_completions_brute(), (0, 16));
+        // A blue row is friendly regardless of the free cells
+        let grid: PartialGrid = "BBB\n???\n".parse().unwrap();
+        assert_eq!(grid.count_completions(), (8, 8));
+        assert_eq!(grid.friendly_probability(), 1.0);
+        // The only friendly completion fills in the gap
+        let grid: PartialGrid = "B?B\n...\n".parse().unwrap();
+        assert_eq!(grid.count_completions(), (1, 2));
+        assert_eq!(grid.friendly_probability(), 0.5);
+    }
+
+    #[test]
+    fn test_zero_cols() {
+        let grid = PartialGrid::new_free(3, 0);
+        assert_eq!(grid.count_completions_dp(), (0, 1));
+    }
+}
@@ -0,0 +1,282 @@
+/*
+    Progress reporting for long-running counts
+
+    Counters report to a ProgressObserver, which decides how (and how often)
+    to display progress: a terminal progress bar for the binaries, a log
+    file, or nothing at all.
+*/
+
+use std::fmt;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub done: u128,
+    pub total: u128,
+    pub friendly: u128,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.done as f64) / (self.total as f64)
+        }
+    }
+
+    // Estimated time remaining, assuming a constant rate
+    pub fn eta(&self) -> Option<Duration> {
+        if self.done == 0 {
+            None
+        } else {
+            let remaining = (self.total - self.done) as f64 / self.done as f64;
+            Some(self.elapsed.mul_f64(remaining))
+        }
+    }
+}
+
+pub trait ProgressObserver {
+    // Number of evenly spaced updates to receive (0 for none)
+    fn num_updates(&self) -> u128;
+    fn update(&mut self, progress: &Progress);
+    fn finish(&mut self, _progress: &Progress) {}
+}
+
+/*
+    Helper for counters: calls the observer at the right times
+*/
+pub struct Tracker<'a, O: ProgressObserver + ?Sized> {
+    observer: &'a mut O,
+    start: Instant,
+    total: u128,
+    step: u128,
+}
+
+impl<'a, O: ProgressObserver + ?Sized> Tracker<'a, O> {
+    pub fn new(observer: &'a mut O, total: u128) -> Self {
+        let step = total.checked_div(observer.num_updates()).unwrap_or(0);
+        Self { observer, start: Instant::now(), total, step }
+    }
+
+    fn progress(&self, done: u128, friendly: u128) -> Progress {
+        let elapsed = self.start.elapsed();
+        Progress { done, total: self.total, friendly, elapsed }
+    }
+
+    pub fn tick(&mut self, done: u128, friendly: u128) {
+        if self.step > 0 && done.is_multiple_of(self.step) {
+            let progress = self.progress(done, friendly);
+            self.observer.update(&progress);
+        }
+    }
+
+    pub fn finish(&mut self, done: u128, friendly: u128) {
+        let progress = self.progress(done, friendly);
+        self.observer.finish(&progress);
+    }
+}
+
+/*
+    Observers
+*/
+
+// Report nothing
+pub struct Silent;
+
+impl ProgressObserver for Silent {
+    fn num_updates(&self) -> u128 {
+        0
+    }
+    fn update(&mut self, _progress: &Progress) {}
+}
+
+// Progress bar on stderr, redrawn in place
+pub struct ProgressBar {
+    width: usize,
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        Self { width: 40 }
+    }
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressObserver for ProgressBar {
+    fn num_updates(&self) -> u128 {
+        1000
+    }
+    fn update(&mut self, progress: &Progress) {
+        let filled = (progress.fraction() * self.width as f64) as usize;
+        let eta = progress
+            .eta()
+            .map(|eta| format!("{:.1?}", eta))
+            .unwrap_or_else(|| "?".to_string());
+        // Progress output is best-effort, so write errors are ignored
+        let mut err = io::stderr();
+        let _ = write!(
+            err,
+            "\r[{}{}] {:5.1}% ({} friendly) elapsed {:.1?}, ETA {}   ",
+            "=".repeat(filled),
+            " ".repeat(self.width - filled.min(self.width)),
+            progress.fraction() * 100.0,
+            progress.friendly,
+            progress.elapsed,
+            eta,
+        );
+        let _ = err.flush();
+    }
+    fn finish(&mut self, progress: &Progress) {
+        if progress.done > 0 {
+            self.update(progress);
+            let _ = writeln!(io::stderr());
+        }
+    }
+}
+
+// One line per update, to any writer (e.g. a log file). A write error
+// doesn't stop the count: the log stops at the first one, which is kept
+// for the caller to check afterwards (see error).
+pub struct LogProgress<W: Write> {
+    out: W,
+    num_updates: u128,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> LogProgress<W> {
+    pub fn new(out: W, num_updates: u128) -> Self {
+        Self { out, num_updates, error: None }
+    }
+
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    fn log(&mut self, line: fmt::Arguments) {
+        if self.error.is_none() {
+            if let Err(err) = writeln!(self.out, "{}", line) {
+                self.error = Some(err);
+            }
+        }
+    }
+}
+
+impl<W: Write> ProgressObserver for LogProgress<W> {
+    fn num_updates(&self) -> u128 {
+        self.num_updates
+    }
+    fn update(&mut self, progress: &Progress) {
+        let eta = progress
+            .eta()
+            .map(|eta| format!("{:.1?}", eta))
+            .unwrap_or_else(|| "?".to_string());
+        self.log(format_args!(
+            "Progress: {}/{} ({:.0}%), {} friendly, elapsed {:.1?}, ETA {}",
+            progress.done,
+            progress.total,
+            progress.fraction() * 100.0,
+            progress.friendly,
+            progress.elapsed,
+            eta,
+        ));
+    }
+    fn finish(&mut self, progress: &Progress) {
+        self.log(format_args!(
+            "Done: {}/{} friendly, elapsed {:.1?}",
+            progress.friendly, progress.total, progress.elapsed,
+        ));
+        if self.error.is_none() {
+            self.error = self.out.flush().err();
+        }
+    }
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fraction_and_eta() {
+        let elapsed = Duration::from_secs(10);
+        let progress = Progress { done: 25, total: 100, friendly: 3, elapsed };
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+        let progress = Progress { done: 0, ..progress };
+        assert_eq!(progress.eta(), None);
+        let progress = Progress { total: 0, ..progress };
+        assert_eq!(progress.fraction(), 1.0);
+    }
+
+    #[test]
+    fn test_tracker_updates() {
+        let mut log = LogProgress::new(Vec::new(), 4);
+        let mut tracker = Tracker::new(&mut log, 100);
+        for done in 1..=100 {
+            tracker.tick(done, done / 2);
+        }
+        tracker.finish(100, 50);
+        let text = String::from_utf8(log.out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        // One update every 25, then one line when done
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("Progress: 25/100 (25%), 12 friendly"));
+        assert!(lines[3].starts_with("Progress: 100/100 (100%)"));
+        assert!(lines[4].starts_with("Done: 50/100 friendly"));
+    }
+
+    // Accepts a fixed number of writes, then fails
+    struct Full(usize);
+
+    impl Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::Error::other("disk full"));
+            }
+            self.0 -= 1;
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_log_errors() {
+        let mut log = LogProgress::new(Full(1), 4);
+        let mut tracker = Tracker::new(&mut log, 100);
+        for done in 1..=100 {
+            tracker.tick(done, 0);
+        }
+        tracker.finish(100, 0);
+        assert_eq!(log.error().unwrap().to_string(), "disk full");
+        let mut log = LogProgress::new(Vec::new(), 4);
+        Tracker::new(&mut log, 100).finish(100, 0);
+        assert!(log.error().is_none());
+    }
+
+    #[test]
+    fn test_tracker_silent() {
+        // More updates than steps, or none at all, should not divide by 0
+        let mut log = LogProgress::new(Vec::new(), 10);
+        let mut tracker = Tracker::new(&mut log, 5);
+        for done in 1..=5 {
+            tracker.tick(done, 0);
+        }
+        assert!(log.out.is_empty());
+        let mut silent = Silent;
+        let mut tracker = Tracker::new(&mut silent, 5);
+        tracker.tick(5, 0);
+        tracker.finish(5, 0);
+    }
+}
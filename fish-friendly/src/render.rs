@@ -0,0 +1,332 @@
+/*
+    Rendering grids as images (SVG or PPM)
+
+    Both formats are drawn from the same list of filled rectangles:
+    the cells (blue, water, or one color per connected component),
+    then the path found by the fish, as a line through the cell centers.
+*/
+
+use super::Grid;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+type Color = (u8, u8, u8);
+
+const BLUE: Color = (48, 112, 208);
+const WATER: Color = (232, 232, 224);
+const PATH: Color = (240, 128, 32);
+const BORDER: Color = (255, 255, 255);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageFormat {
+    Svg,
+    Ppm,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Svg => "svg",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(ImageFormat::Svg),
+            "ppm" => Ok(ImageFormat::Ppm),
+            _ => Err(format!("Unknown image format {:?} (svg or ppm)", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    // Side length of each cell, in pixels (at least 1, see new)
+    cell_size: usize,
+    pub show_path: bool,
+    // Color each connected component of blue cells differently
+    pub color_components: bool,
+}
+
+impl RenderOptions {
+    pub fn new(cell_size: usize) -> Result<Self, String> {
+        if cell_size == 0 {
+            return Err("The cell size should be at least 1 pixel".to_string());
+        }
+        Ok(Self { cell_size, ..Default::default() })
+    }
+
+    pub fn cell_size(&self) -> usize {
+        self.cell_size
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { cell_size: 20, show_path: true, color_components: false }
+    }
+}
+
+// Well-separated colors: hues spaced by the golden angle
+fn component_color(k: usize) -> Color {
+    let hue = (k as f64 * 137.507_764) % 360.0;
+    let (s, v) = (0.55, 0.85);
+    let c = v * s;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as usize {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    let to_u8 = |y: f64| ((y + m) * 255.0).round() as u8;
+    (to_u8(r), to_u8(g), to_u8(b))
+}
+
+struct Rect {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    color: Color,
+}
+
+impl Grid {
+    fn image_size(&self, options: &RenderOptions) -> (usize, usize) {
+        (self.cols * options.cell_size, self.rows * options.cell_size)
+    }
+
+    fn rects(&self, options: &RenderOptions) -> Vec<Rect> {
+        let size = options.cell_size;
+        let (width, height) = self.image_size(options);
+        let mut result =
+            vec![Rect { x: 0, y: 0, width, height, color: BORDER }];
+
+        let labels = if options.color_components {
+            Some(self.components().0)
+        } else {
+            None
+        };
+        // Leave a 1 pixel border between cells (if there is room)
+        let gap = if size > 2 { 1 } else { 0 };
+        for i in 1..=self.rows {
+            for j in 1..=self.cols {
+                let color = match (&labels, self.cell(i, j)) {
+                    (_, false) => WATER,
+                    (None, true) => BLUE,
+                    (Some(labels), true) => {
+                        component_color(labels[i][j].unwrap())
+                    }
+                };
+                result.push(Rect {
+                    x: (j - 1) * size,
+                    y: (i - 1) * size,
+                    width: size - gap,
+                    height: size - gap,
+                    color,
+                });
+            }
+        }
+
+        if options.show_path {
+            if let Some(path) = self.fish_path() {
+                let thickness = (size / 4).max(1);
+                let center = |k: usize| (k - 1) * size + (size - thickness) / 2;
+                // Extend the line to the left and right edges
+                let first = path[0];
+                let last = path[path.len() - 1];
+                result.push(Rect {
+                    x: 0,
+                    y: center(first.0),
+                    width: center(first.1) + thickness,
+                    height: thickness,
+                    color: PATH,
+                });
+                result.push(Rect {
+                    x: center(last.1),
+                    y: center(last.0),
+                    width: width - center(last.1),
+                    height: thickness,
+                    color: PATH,
+                });
+                for step in path.windows(2) {
+                    let (y0, y1) = (center(step[0].0), center(step[1].0));
+                    let (x0, x1) = (center(step[0].1), center(step[1].1));
+                    result.push(Rect {
+                        x: x0.min(x1),
+                        y: y0.min(y1),
+                        width: x0.abs_diff(x1) + thickness,
+                        height: y0.abs_diff(y1) + thickness,
+                        color: PATH,
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    pub fn to_svg(&self, options: &RenderOptions) -> String {
+        let (width, height) = self.image_size(options);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+             width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+            width, height
+        );
+        for rect in self.rects(options) {
+            let (r, g, b) = rect.color;
+            svg.push_str(&format!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" \
+                 fill=\"#{:02x}{:02x}{:02x}\"/>\n",
+                rect.x, rect.y, rect.width, rect.height, r, g, b
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    // Binary PPM (P6)
+    pub fn write_ppm<W: Write>(
+        &self,
+        out: &mut W,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let (width, height) = self.image_size(options);
+        let mut pixels = vec![0_u8; width * height * 3];
+        for rect in self.rects(options) {
+            let (r, g, b) = rect.color;
+            for y in rect.y..(rect.y + rect.height).min(height) {
+                for x in rect.x..(rect.x + rect.width).min(width) {
+                    let k = (y * width + x) * 3;
+                    pixels[k..(k + 3)].copy_from_slice(&[r, g, b]);
+                }
+            }
+        }
+        write!(out, "P6\n{} {}\n255\n", width, height)?;
+        out.write_all(&pixels)
+    }
+
+    pub fn save_image(
+        &self,
+        path: &Path,
+        format: ImageFormat,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        match format {
+            ImageFormat::Svg => {
+                out.write_all(self.to_svg(options).as_bytes())?
+            }
+            ImageFormat::Ppm => self.write_ppm(&mut out, options)?,
+        }
+        out.flush()
+    }
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ppm_pixels(grid: &Grid, options: &RenderOptions) -> Vec<u8> {
+        let mut out = Vec::new();
+        grid.write_ppm(&mut out, options).unwrap();
+        let (width, height) = grid.image_size(options);
+        let header = format!("P6\n{} {}\n255\n", width, height);
+        assert!(out.starts_with(header.as_bytes()));
+        assert_eq!(out.len(), header.len() + width * height * 3);
+        out[header.len()..].to_vec()
+    }
+
+    fn pixel(pixels: &[u8], width: usize, x: usize, y: usize) -> Color {
+        let k = (y * width + x) * 3;
+        (pixels[k], pixels[k + 1], pixels[k + 2])
+    }
+
+    #[test]
+    fn test_options() {
+        assert!(RenderOptions::new(0).is_err());
+        assert_eq!(RenderOptions::new(1).unwrap().cell_size(), 1);
+        assert_eq!("ppm".parse(), Ok(ImageFormat::Ppm));
+        assert_eq!("svg".parse(), Ok(ImageFormat::Svg));
+        assert!("png".parse::<ImageFormat>().is_err());
+    }
+
+    #[test]
+    fn test_svg() {
+        let grid: Grid = "BB.\n.BB\n".parse().unwrap();
+        let options = RenderOptions::new(10).unwrap();
+        let svg = grid.to_svg(&options);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("width=\"30\" height=\"20\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        // Background, 6 cells, 2 edge segments and 3 steps of the path
+        assert_eq!(svg.matches("<rect").count(), 1 + 6 + 2 + 3);
+        let options = RenderOptions { show_path: false, ..options };
+        assert_eq!(grid.to_svg(&options).matches("<rect").count(), 1 + 6);
+    }
+
+    #[test]
+    fn test_ppm() {
+        let grid: Grid = "B.\n.B\n".parse().unwrap();
+        let options = RenderOptions::new(10).unwrap();
+        let pixels = ppm_pixels(&grid, &options);
+        assert_eq!(pixel(&pixels, 20, 5, 5), BLUE);
+        assert_eq!(pixel(&pixels, 20, 15, 5), WATER);
+        // The gap between cells
+        assert_eq!(pixel(&pixels, 20, 9, 5), BORDER);
+        // The path goes through the center of the blue cells
+        let grid: Grid = "BB\n..\n".parse().unwrap();
+        let pixels = ppm_pixels(&grid, &options);
+        assert_eq!(pixel(&pixels, 20, 0, 4), PATH);
+        assert_eq!(pixel(&pixels, 20, 19, 4), PATH);
+        assert_eq!(pixel(&pixels, 20, 5, 15), WATER);
+    }
+
+    #[test]
+    fn test_small_cells() {
+        // No gap and a path of thickness 1
+        let grid: Grid = "BB\nB.\n".parse().unwrap();
+        for cell_size in 1..=3 {
+            let options = RenderOptions::new(cell_size).unwrap();
+            ppm_pixels(&grid, &options);
+        }
+        let options = RenderOptions::new(1).unwrap();
+        let pixels =
+            ppm_pixels(&grid, &RenderOptions { show_path: false, ..options });
+        assert_eq!(pixel(&pixels, 2, 0, 1), BLUE);
+        assert_eq!(pixel(&pixels, 2, 1, 1), WATER);
+    }
+
+    #[test]
+    fn test_components() {
+        let grid: Grid = "B.B\n...\nBBB\n".parse().unwrap();
+        let options = RenderOptions {
+            color_components: true,
+            ..RenderOptions::new(10).unwrap()
+        };
+        let pixels = ppm_pixels(&grid, &options);
+        let colors: Vec<Color> = [(5, 5), (25, 5), (5, 25), (25, 25)]
+            .iter()
+            .map(|&(x, y)| pixel(&pixels, 30, x, y))
+            .collect();
+        // Three components, one of which has two cells
+        assert_ne!(colors[0], colors[1]);
+        assert_ne!(colors[0], colors[2]);
+        assert_ne!(colors[1], colors[2]);
+        assert_eq!(colors[2], colors[3]);
+        assert!(colors.iter().all(|&c| c != BLUE && c != WATER));
+    }
+}
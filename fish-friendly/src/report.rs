@@ -0,0 +1,238 @@
+/*
+    Structured results of counts
+
+    A CountResult records what was counted (grid size and move rule), the
+    result, and how it was obtained, so that binaries can print it as text
+    or as JSON for downstream scripts.
+*/
+
+use super::progress::{ProgressObserver, Tracker};
+use super::transfer::{TransferMatrix, MAX_ROWS};
+use super::{count_friendly_grids_with, Algorithm, Grid, MoveRule, MOVE_RULE};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Method {
+    // Check every grid
+    BruteForce,
+    // Iterate the transfer matrix (see transfer.rs)
+    TransferMatrix,
+    // Estimate from uniformly random grids (total is the number of samples)
+    RandomSampling,
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "brute-force" => Ok(Method::BruteForce),
+            "transfer-matrix" => Ok(Method::TransferMatrix),
+            "random-sampling" => Ok(Method::RandomSampling),
+            _ => Err(format!(
+                "Unknown method {:?} (brute-force, transfer-matrix or \
+                 random-sampling)",
+                s
+            )),
+        }
+    }
+}
+
+// The methods that count exactly (see CountResult::count)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CountMethod {
+    BruteForce,
+    TransferMatrix,
+}
+
+impl From<CountMethod> for Method {
+    fn from(method: CountMethod) -> Self {
+        match method {
+            CountMethod::BruteForce => Method::BruteForce,
+            CountMethod::TransferMatrix => Method::TransferMatrix,
+        }
+    }
+}
+
+impl TryFrom<Method> for CountMethod {
+    type Error = String;
+
+    fn try_from(method: Method) -> Result<Self, Self::Error> {
+        match method {
+            Method::BruteForce => Ok(CountMethod::BruteForce),
+            Method::TransferMatrix => Ok(CountMethod::TransferMatrix),
+            Method::RandomSampling => Err("Random sampling needs a number \
+                                           of samples (use the random binary)"
+                .to_string()),
+        }
+    }
+}
+
+impl FromStr for CountMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Method>()?.try_into()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CountResult {
+    pub rows: usize,
+    pub cols: usize,
+    pub move_rule: MoveRule,
+    pub friendly: u128,
+    pub total: u128,
+    // Serialized as a number of seconds
+    #[serde(rename = "elapsed_secs")]
+    #[serde(serialize_with = "serialize_secs")]
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub elapsed: Duration,
+    pub method: Method,
+    // Algorithm used to check each grid (None for the transfer matrix)
+    pub algorithm: Option<Algorithm>,
+}
+
+fn serialize_secs<S: Serializer>(
+    d: &Duration,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
+}
+
+fn deserialize_secs<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Duration, D::Error> {
+    let secs = f64::deserialize(d)?;
+    Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+}
+
+impl CountResult {
+    // Count using the given method, reporting progress to the observer
+    // (only used for brute force). Random sampling needs a number of
+    // samples, so it has its own constructor (see sample).
+    pub fn count<O: ProgressObserver + ?Sized>(
+        rows: usize,
+        cols: usize,
+        method: CountMethod,
+        observer: &mut O,
+    ) -> Result<Self, String> {
+        let total = 2_u128
+            .checked_pow((rows * cols) as u32)
+            .ok_or("The number of grids is too large for a u128")?;
+        let start = Instant::now();
+        let friendly = match method {
+            CountMethod::BruteForce => {
+                count_friendly_grids_with(rows, cols, observer).0
+            }
+            CountMethod::TransferMatrix => {
+                if !MOVE_RULE.backwards() {
+                    return Err("The transfer matrix assumes backwards steps \
+                                are allowed"
+                        .to_string());
+                }
+                if !(1..=MAX_ROWS).contains(&rows) {
+                    return Err(format!(
+                        "The transfer matrix needs between 1 and {} rows",
+                        MAX_ROWS
+                    ));
+                }
+                TransferMatrix::new(rows).count_friendly(cols)
+            }
+        };
+        let algorithm = match method {
+            CountMethod::BruteForce => Some(Algorithm::Dfs),
+            CountMethod::TransferMatrix => None,
+        };
+        let method = method.into();
+        let elapsed = start.elapsed();
+        Ok(Self::new(rows, cols, method, algorithm, friendly, total, elapsed))
+    }
+
+    // Estimate from num_samples random grids, checked with the given
+    // algorithm, reporting progress to the observer. Each grid is also
+    // passed to on_grid, with its index.
+    pub fn sample<O, F>(
+        rows: usize,
+        cols: usize,
+        num_samples: usize,
+        algorithm: Algorithm,
+        observer: &mut O,
+        mut on_grid: F,
+    ) -> Result<Self, String>
+    where
+        O: ProgressObserver + ?Sized,
+        F: FnMut(usize, &Grid),
+    {
+        if !algorithm.supported() {
+            return Err(format!(
+                "{} does not support the move rule {:?}",
+                algorithm, MOVE_RULE
+            ));
+        }
+        let start = Instant::now();
+        let mut tracker = Tracker::new(observer, num_samples as u128);
+        let mut friendly: u128 = 0;
+        for sample in 0..num_samples {
+            let grid = Grid::new_random(rows, cols);
+            if grid.fish_friendly_using(algorithm) {
+                friendly += 1;
+            }
+            on_grid(sample, &grid);
+            tracker.tick(sample as u128 + 1, friendly);
+        }
+        tracker.finish(num_samples as u128, friendly);
+        let total = num_samples as u128;
+        let method = Method::RandomSampling;
+        let algorithm = Some(algorithm);
+        let elapsed = start.elapsed();
+        Ok(Self::new(rows, cols, method, algorithm, friendly, total, elapsed))
+    }
+
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        method: Method,
+        algorithm: Option<Algorithm>,
+        friendly: u128,
+        total: u128,
+        elapsed: Duration,
+    ) -> Self {
+        Self {
+            rows,
+            cols,
+            move_rule: MOVE_RULE,
+            friendly,
+            total,
+            elapsed,
+            method,
+            algorithm,
+        }
+    }
+
+    pub fn fraction(&self) -> f64 {
+        (self.friendly as f64) / (self.total as f64)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl fmt::Display for CountResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "=== Results for {} x {} grids ===", self.rows, self.cols)?;
+        write!(
+            f,
+            "The fish can swim across in {} of {} cases ({:.3}%).",
+            self.friendly,
+            self.total,
+            self.fraction() * 100.0,
+        )
+    }
+}
@@ -0,0 +1,239 @@
+/*
+    Multi-species grids
+
+    Generalizes Grid from two colors (blue or not) to k colors 0..k.
+    Each species of fish can swim through some set of colors, given as a
+    bitmask. The usual problem is 2 colors and a single species with mask
+    0b10 (swims only through color 1).
+
+    standard_species sets up the common case: color 0 is water that no
+    fish can swim through, each species has its own color, and some
+    additional shared colors can be swum through by every species.
+*/
+
+use super::progress::{ProgressObserver, Silent, Tracker};
+use super::{dfs, Grid};
+
+// Colors are stored as u8, and species masks as u64
+pub const MAX_COLORS: usize = 64;
+
+// Color of the cells around the border, which no fish can swim through
+const BORDER: u8 = u8::MAX;
+
+// Returns (number of colors, mask for each species)
+pub fn standard_species(
+    num_species: usize,
+    num_shared: usize,
+) -> (usize, Vec<u64>) {
+    let colors = 1 + num_species + num_shared;
+    assert!(
+        colors <= MAX_COLORS,
+        "Too many colors: {} (max {})",
+        colors,
+        MAX_COLORS
+    );
+    let shared: u64 = ((1 + num_species)..colors).map(|c| 1 << c).sum();
+    let masks = (0..num_species).map(|s| (1 << (s + 1)) | shared).collect();
+    (colors, masks)
+}
+
+// A Grid with u8 colors, sharing its moves and border with Grid
+pub struct MultiGrid {
+    colors: usize,
+    grid: Grid<u8>,
+}
+
+impl MultiGrid {
+    pub fn new_empty(rows: usize, cols: usize, colors: usize) -> Self {
+        assert!((1..=MAX_COLORS).contains(&colors));
+        Self { colors, grid: Grid::new_with_border(rows, cols, BORDER) }
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.grid.get_rows()
+    }
+
+    pub fn get_cols(&self) -> usize {
+        self.grid.get_cols()
+    }
+
+    pub fn cell(&self, i: usize, j: usize) -> u8 {
+        self.grid.grid[i][j]
+    }
+
+    pub fn set_cell(&mut self, i: usize, j: usize, color: u8) {
+        assert!((color as usize) < self.colors);
+        self.grid.grid[i][j] = color;
+    }
+
+    pub fn sources(
+        &self,
+        mask: u64,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.grid.sources_where(move |color| can_swim(mask, color))
+    }
+
+    pub fn is_sink(&self, i: usize, j: usize) -> bool {
+        self.grid.is_sink(i, j)
+    }
+
+    pub fn adjacencies(
+        &self,
+        mask: u64,
+        i: usize,
+        j: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.grid.adjacencies_where(i, j, move |color| can_swim(mask, color))
+    }
+
+    // Check if a fish that swims through the colors in mask can get across
+    pub fn fish_friendly(&self, mask: u64) -> bool {
+        dfs(
+            self.sources(mask),
+            |&(i, j)| self.adjacencies(mask, i, j),
+            |&(i, j)| self.is_sink(i, j),
+        )
+    }
+
+    // Iterate over all grids
+    // return false in case of overflow
+    pub fn rotate(&mut self) -> bool {
+        let colors = self.colors;
+        self.grid.rotate_with(|color| {
+            if color as usize + 1 == colors {
+                0
+            } else {
+                color + 1
+            }
+        })
+    }
+}
+
+fn can_swim(mask: u64, color: u8) -> bool {
+    color != BORDER && mask & (1 << color) != 0
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiCount {
+    // Number of grids in which each species can cross
+    pub per_species: Vec<u128>,
+    // Number of grids in which every species can cross
+    pub all: u128,
+    // Number of grids in which at least one species can cross
+    pub any: u128,
+    pub total: u128,
+}
+
+// Count grids by which species can cross, without reporting progress
+pub fn count_multi_species(
+    rows: usize,
+    cols: usize,
+    colors: usize,
+    masks: &[u64],
+) -> MultiCount {
+    count_multi_species_with(rows, cols, colors, masks, &mut Silent)
+}
+
+// Count grids by which species can cross, reporting progress (with the
+// number of grids in which all species can cross) to the observer
+pub fn count_multi_species_with<O: ProgressObserver + ?Sized>(
+    rows: usize,
+    cols: usize,
+    colors: usize,
+    masks: &[u64],
+    observer: &mut O,
+) -> MultiCount {
+    let expect_total: u128 = (colors as u128)
+        .checked_pow((rows * cols) as u32)
+        .expect("The number of grids is too large to fit in a u128");
+    let mut tracker = Tracker::new(observer, expect_total);
+
+    let mut result = MultiCount {
+        per_species: vec![0; masks.len()],
+        all: 0,
+        any: 0,
+        total: 0,
+    };
+    let mut grid = MultiGrid::new_empty(rows, cols, colors);
+    loop {
+        let mut all = true;
+        let mut any = false;
+        for (s, &mask) in masks.iter().enumerate() {
+            if grid.fish_friendly(mask) {
+                result.per_species[s] += 1;
+                any = true;
+            } else {
+                all = false;
+            }
+        }
+        result.all += all as u128;
+        result.any += any as u128;
+        result.total += 1;
+        tracker.tick(result.total, result.all);
+        if !grid.rotate() {
+            break;
+        }
+    }
+    debug_assert_eq!(result.total, expect_total);
+    tracker.finish(result.total, result.all);
+
+    result
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str, colors: usize) -> MultiGrid {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut grid =
+            MultiGrid::new_empty(lines.len(), lines[0].len(), colors);
+        for (i, line) in lines.iter().enumerate() {
+            for (j, c) in line.chars().enumerate() {
+                grid.set_cell(i + 1, j + 1, c.to_digit(10).unwrap() as u8);
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn test_standard_species() {
+        assert_eq!(standard_species(1, 0), (2, vec![0b10]));
+        assert_eq!(standard_species(2, 1), (4, vec![0b1010, 0b1100]));
+    }
+
+    #[test]
+    fn test_fish_friendly() {
+        let (colors, masks) = standard_species(2, 1);
+        let grid = parse("113\n022\n", colors);
+        assert!(grid.fish_friendly(masks[0]));
+        assert!(!grid.fish_friendly(masks[1]));
+        let grid = parse("130\n223\n", colors);
+        assert!(!grid.fish_friendly(masks[0]));
+        assert!(grid.fish_friendly(masks[1]));
+        // Nobody swims through water, or the border
+        let grid = parse("303\n", colors);
+        assert!(!grid.fish_friendly(0b1111 & !1));
+        assert!(grid.fish_friendly(0b1111));
+    }
+
+    #[test]
+    fn test_count_multi_species() {
+        // Two species and no shared colors on a 1 x 2 grid: each species
+        // crosses when both cells have its color
+        let (colors, masks) = standard_species(2, 0);
+        let result = count_multi_species(1, 2, colors, &masks);
+        assert_eq!(result.total, 9);
+        assert_eq!(result.per_species, vec![1, 1]);
+        assert_eq!((result.all, result.any), (0, 2));
+        // A shared color that both species can use
+        let (colors, masks) = standard_species(2, 1);
+        let result = count_multi_species(1, 2, colors, &masks);
+        assert_eq!(result.total, 16);
+        assert_eq!(result.per_species, vec![4, 4]);
+        assert_eq!((result.all, result.any), (1, 7));
+    }
+}
@@ -0,0 +1,244 @@
+/*
+    Tides: grids that change while the fish swims
+
+    Cells flip between blue and water over time, according to a schedule:
+        - Periodic: each cell flips once every `period` ticks, with its own
+          (random) phase. The grid then repeats every 2 * period ticks.
+        - Random: at each tick, each cell flips independently with
+          probability `rate`.
+
+    The fish may wait on the left bank and enter the first column at any
+    time. At each tick it moves to an adjacent cell (same moves as
+    Grid::adjacencies) or waits in place, and the cell it ends up in must be
+    blue at the new time. It has crossed once it reaches the last column.
+
+    We decide this with a search on the time-expanded graph, whose nodes are
+    (row, column, time). For a periodic schedule, time is taken modulo the
+    period of the grid, so the search is exact; for a random schedule, the
+    fish must cross within a fixed horizon.
+*/
+
+use super::{dfs, Grid};
+
+use rand::Rng;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Schedule {
+    Periodic { period: usize },
+    Random { rate: f64 },
+}
+
+impl Schedule {
+    // Probability that a given cell flips at a given tick
+    pub fn flip_rate(&self) -> f64 {
+        match *self {
+            Schedule::Periodic { period } => 1.0 / period as f64,
+            Schedule::Random { rate } => rate,
+        }
+    }
+}
+
+pub struct TideGrid {
+    cols: usize,
+    // The grid at each time
+    frames: Vec<Grid>,
+    // If true, frames repeat forever; otherwise time stops at the last frame
+    periodic: bool,
+}
+
+impl TideGrid {
+    // horizon is only used for random schedules: the number of ticks to
+    // simulate after time 0
+    #[allow(clippy::needless_range_loop)]
+    pub fn new<R: Rng>(
+        initial: &Grid,
+        schedule: &Schedule,
+        horizon: usize,
+        rng: &mut R,
+    ) -> Self {
+        let (rows, cols) = (initial.rows, initial.cols);
+        let mut frames = vec![initial.clone()];
+        match *schedule {
+            Schedule::Periodic { period } => {
+                assert!(period > 0, "Period should be positive");
+                let phases: Vec<Vec<usize>> = (0..=rows)
+                    .map(|_| {
+                        (0..=cols).map(|_| rng.gen_range(0..period)).collect()
+                    })
+                    .collect();
+                for t in 1..(2 * period) {
+                    let mut frame = frames[t - 1].clone();
+                    for i in 1..=rows {
+                        for j in 1..=cols {
+                            if (t + phases[i][j]).is_multiple_of(period) {
+                                frame.grid[i][j] = !frame.grid[i][j];
+                            }
+                        }
+                    }
+                    frames.push(frame);
+                }
+            }
+            Schedule::Random { rate } => {
+                for t in 1..=horizon {
+                    let mut frame = frames[t - 1].clone();
+                    for i in 1..=rows {
+                        for j in 1..=cols {
+                            if rng.gen_bool(rate) {
+                                frame.grid[i][j] = !frame.grid[i][j];
+                            }
+                        }
+                    }
+                    frames.push(frame);
+                }
+            }
+        }
+        let periodic = matches!(schedule, Schedule::Periodic { .. });
+        Self { cols, frames, periodic }
+    }
+
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn frame(&self, t: usize) -> &Grid {
+        &self.frames[t]
+    }
+
+    // Time after t, if any (for periodic grids, modulo the period)
+    fn next_time(&self, t: usize) -> Option<usize> {
+        if t + 1 < self.frames.len() {
+            Some(t + 1)
+        } else if self.periodic {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn sources(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.frames
+            .iter()
+            .enumerate()
+            .flat_map(|(t, frame)| frame.sources().map(move |(i, j)| (i, j, t)))
+    }
+
+    fn successors(
+        &self,
+        i: usize,
+        j: usize,
+        t: usize,
+    ) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.next_time(t).into_iter().flat_map(move |t| {
+            let frame = self.frame(t);
+            frame
+                .adjacencies(i, j)
+                .chain(Some((i, j)).filter(|&(i, j)| frame.cell(i, j)))
+                .map(move |(i, j)| (i, j, t))
+        })
+    }
+
+    pub fn fish_can_cross(&self) -> bool {
+        dfs(
+            self.sources(),
+            |&(i, j, t)| self.successors(i, j, t),
+            |&(_, j, _)| j == self.cols,
+        )
+    }
+}
+
+// Monte Carlo estimate of the probability that the fish can cross a
+// uniformly random initial grid, under the given schedule
+pub fn crossing_probability<R: Rng>(
+    rows: usize,
+    cols: usize,
+    schedule: &Schedule,
+    horizon: usize,
+    num_samples: usize,
+    rng: &mut R,
+) -> f64 {
+    let mut crossed = 0;
+    for _ in 0..num_samples {
+        let initial = Grid::new_random_with(rows, cols, rng);
+        if TideGrid::new(&initial, schedule, horizon, rng).fish_can_cross() {
+            crossed += 1;
+        }
+    }
+    crossed as f64 / num_samples as f64
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn tide_grid(frames: &[&str], periodic: bool) -> TideGrid {
+        let frames: Vec<Grid> =
+            frames.iter().map(|text| text.parse().unwrap()).collect();
+        TideGrid { cols: frames[0].cols, frames, periodic }
+    }
+
+    #[test]
+    fn test_flip_rate() {
+        assert_eq!(Schedule::Periodic { period: 4 }.flip_rate(), 0.25);
+        assert_eq!(Schedule::Random { rate: 0.1 }.flip_rate(), 0.1);
+    }
+
+    #[test]
+    fn test_waiting() {
+        // The fish waits in the first column for the second to fill in
+        assert!(!tide_grid(&["B.\n"], false).fish_can_cross());
+        assert!(tide_grid(&["B.\n", "BB\n"], false).fish_can_cross());
+        // The fish can enter at any time, but can't wait in water
+        assert!(tide_grid(&["..\n", "B.\n", ".B\n"], false).fish_can_cross());
+        assert!(!tide_grid(&[".B\n", "B.\n"], false).fish_can_cross());
+        // Unless time wraps around
+        assert!(tide_grid(&[".B\n", "B.\n"], true).fish_can_cross());
+    }
+
+    #[test]
+    fn test_periodic_frames() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let initial = Grid::new_random_with(3, 4, &mut rng);
+        let schedule = Schedule::Periodic { period: 3 };
+        let tides = TideGrid::new(&initial, &schedule, 0, &mut rng);
+        assert_eq!(tides.num_frames(), 6);
+        // Each cell flips exactly twice per cycle, once per period
+        for i in 1..=3 {
+            for j in 1..=4 {
+                let flips: Vec<usize> = (0..6)
+                    .filter(|&t| {
+                        let next = tides.frame((t + 1) % 6);
+                        tides.frame(t).cell(i, j) != next.cell(i, j)
+                    })
+                    .collect();
+                assert_eq!(flips.len(), 2);
+                assert_eq!(flips[1] - flips[0], 3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_frames() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let initial = Grid::new_random_with(4, 4, &mut rng);
+            // Without flips, this is the original problem (as long as
+            // the horizon leaves time for a path through every cell)
+            let still = Schedule::Random { rate: 0.0 };
+            let tides = TideGrid::new(&initial, &still, 16, &mut rng);
+            assert_eq!(tides.num_frames(), 17);
+            assert_eq!(tides.fish_can_cross(), initial.fish_friendly());
+            // Flipping every cell at every tick alternates two grids
+            let flip = Schedule::Random { rate: 1.0 };
+            let tides = TideGrid::new(&initial, &flip, 2, &mut rng);
+            assert_eq!(tides.frame(2).to_string(), initial.to_string());
+        }
+        let schedule = Schedule::Random { rate: 0.0 };
+        let p = crossing_probability(1, 1, &schedule, 3, 100, &mut rng);
+        assert!(p > 0.0 && p < 1.0);
+    }
+}
@@ -0,0 +1,334 @@
+/*
+    Transfer matrix for grids with a fixed number of rows
+
+    We read the grid one column at a time. The state after each column
+    records, for each cell in the current column:
+        0: water
+        1: blue and connected to the left edge
+        2, 3, ...: blue and in some other connected component
+    (labels 2, 3, ... are numbered in order of first appearance, so that each
+    state has a unique representation). Connectivity is with respect to all
+    columns read so far, so this handles paths that step backwards.
+    It also relies on them: without backwards steps, being connected is no
+    longer enough to be reachable from the left edge. So this only applies
+    when MOVE_RULE allows backwards steps, which TransferMatrix::new checks.
+
+    A grid is fish-friendly exactly when its last state contains a 1.
+    Once a state has no 1s the grid can never become friendly, so we drop
+    those states. The number of friendly grids is then the sum of the
+    entries of initial * T^(cols - 1), where T is the (nonnegative) matrix
+    on the remaining ("alive") states. Every state is reachable from an
+    initial state (that is how they are found), and every state counts at
+    the end, so this grows like the spectral radius of T (the dominant
+    eigenvalue) as the number of columns grows. We don't need T to be
+    irreducible for this, or for the bounds in growth_rate_bounds.
+*/
+
+use super::MOVE_RULE;
+
+use std::collections::HashMap;
+
+// Cap on the number of rows. The number of states grows quickly, and the
+// certified bounds below use exact u128 arithmetic that needs headroom.
+pub const MAX_ROWS: usize = 14;
+
+pub(crate) type State = Vec<u8>;
+
+pub struct TransferMatrix {
+    rows: usize,
+    states: Vec<State>,
+    // Number of first columns resulting in each state
+    initial: Vec<u128>,
+    // For each state, the list of (next state, number of columns leading there)
+    transitions: Vec<Vec<(usize, u128)>>,
+}
+
+/*
+    Union-find on a small number of nodes
+*/
+pub(crate) fn find(parent: &mut [usize], x: usize) -> usize {
+    let mut root = x;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    let mut curr = x;
+    while parent[curr] != root {
+        let next = parent[curr];
+        parent[curr] = root;
+        curr = next;
+    }
+    root
+}
+
+pub(crate) fn union(parent: &mut [usize], x: usize, y: usize) {
+    let rx = find(parent, x);
+    let ry = find(parent, y);
+    if rx != ry {
+        parent[rx] = ry;
+    }
+}
+
+// Join a column of component labels (0 for water) with a new column to its
+// right (bit i of column = row i is blue). Returns the component of each
+// cell in the new column (None for water), and the component of each label
+// in the old column. Components are numbered arbitrarily.
+#[allow(clippy::needless_range_loop)]
+pub(crate) fn merge_column(
+    labels: &[u8],
+    column: u32,
+) -> (Vec<Option<usize>>, HashMap<u8, usize>) {
+    let rows = labels.len();
+    // Nodes 0..rows: old column; nodes rows..2*rows: new column
+    let mut parent: Vec<usize> = (0..(2 * rows)).collect();
+    let mut first_with_label: HashMap<u8, usize> = HashMap::new();
+    for (i, &label) in labels.iter().enumerate() {
+        if label != 0 {
+            match first_with_label.get(&label) {
+                Some(&j) => union(&mut parent, i, j),
+                None => {
+                    first_with_label.insert(label, i);
+                }
+            }
+        }
+    }
+    let blue = |i: usize| column & (1 << i) != 0;
+    for i in 0..rows {
+        if blue(i) {
+            if labels[i] != 0 {
+                union(&mut parent, i, rows + i);
+            }
+            if i + 1 < rows && blue(i + 1) {
+                union(&mut parent, rows + i, rows + i + 1);
+            }
+        }
+    }
+    let new = (0..rows)
+        .map(|i| Some(find(&mut parent, rows + i)).filter(|_| blue(i)))
+        .collect();
+    let old = first_with_label
+        .into_iter()
+        .map(|(label, i)| (label, find(&mut parent, i)))
+        .collect();
+    (new, old)
+}
+
+// Add one column (bit i of column = row i is blue) to the right of a state.
+// Returns None if nothing in the new column is connected to the left edge.
+pub(crate) fn step(state: &[u8], column: u32) -> Option<State> {
+    let (new, old) = merge_column(state, column);
+    let left_root = old.get(&1);
+    let mut result = vec![0; state.len()];
+    let mut labels: HashMap<usize, u8> = HashMap::new();
+    let mut alive = false;
+    for (cell, root) in result.iter_mut().zip(&new) {
+        if let Some(root) = root {
+            if Some(root) == left_root {
+                *cell = 1;
+                alive = true;
+            } else {
+                let next_label = labels.len() as u8 + 2;
+                *cell = *labels.entry(*root).or_insert(next_label);
+            }
+        }
+    }
+    if alive {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+impl TransferMatrix {
+    pub fn new(rows: usize) -> Self {
+        assert!(
+            MOVE_RULE.backwards(),
+            "The transfer matrix assumes backwards steps are allowed"
+        );
+        assert!(
+            (1..=MAX_ROWS).contains(&rows),
+            "Number of rows must be between 1 and {}",
+            MAX_ROWS
+        );
+        let mut result = Self {
+            rows,
+            states: Vec::new(),
+            initial: Vec::new(),
+            transitions: Vec::new(),
+        };
+        let mut index: HashMap<State, usize> = HashMap::new();
+
+        // In the first column, every blue cell is connected to the left edge
+        for column in 1..(1_u32 << rows) {
+            let state = (0..rows)
+                .map(|i| if column & (1 << i) != 0 { 1 } else { 0 })
+                .collect();
+            let s = result.state_index(&mut index, state);
+            result.initial[s] += 1;
+        }
+
+        // Explore all reachable states
+        let mut s = 0;
+        while s < result.states.len() {
+            let mut counts: HashMap<usize, u128> = HashMap::new();
+            for column in 0..(1_u32 << rows) {
+                if let Some(next) = step(&result.states[s], column) {
+                    let t = result.state_index(&mut index, next);
+                    *counts.entry(t).or_insert(0) += 1;
+                }
+            }
+            let mut succs: Vec<(usize, u128)> = counts.into_iter().collect();
+            succs.sort_unstable();
+            result.transitions[s] = succs;
+            s += 1;
+        }
+        result
+    }
+
+    fn state_index(
+        &mut self,
+        index: &mut HashMap<State, usize>,
+        state: State,
+    ) -> usize {
+        if let Some(&s) = index.get(&state) {
+            return s;
+        }
+        let s = self.states.len();
+        index.insert(state.clone(), s);
+        self.states.push(state);
+        self.initial.push(0);
+        self.transitions.push(Vec::new());
+        s
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.states.len()
+    }
+
+    // Number of friendly rows x cols grids, computed by iterating the matrix.
+    // Agrees with count_friendly_grids, but is linear in cols.
+    pub fn count_friendly(&self, cols: usize) -> u128 {
+        if cols == 0 {
+            return 0;
+        }
+        let mut counts = self.initial.clone();
+        for _ in 1..cols {
+            let mut next = vec![0_u128; self.num_states()];
+            for (s, &count) in counts.iter().enumerate() {
+                if count > 0 {
+                    for &(t, mult) in &self.transitions[s] {
+                        next[t] = count
+                            .checked_mul(mult)
+                            .and_then(|x| x.checked_add(next[t]))
+                            .expect("Number of grids is too large for a u128");
+                    }
+                }
+            }
+            counts = next;
+        }
+        counts.iter().sum()
+    }
+
+    fn apply_f64(&self, x: &[f64]) -> Vec<f64> {
+        self.transitions
+            .iter()
+            .map(|succs| succs.iter().map(|&(t, m)| m as f64 * x[t]).sum())
+            .collect()
+    }
+
+    // Certified bounds on the dominant eigenvalue.
+    //
+    // We first approximate the Perron eigenvector x by power iteration.
+    // Then for any positive vector x, the Collatz-Wielandt bounds give
+    //     min_i (Tx)_i / x_i <= lambda <= max_i (Tx)_i / x_i.
+    // These hold for any nonnegative T, irreducible or not: if Tx <= c x
+    // then T^k x <= c^k x, and if Tx >= c x then T^k x >= c^k x, so the
+    // norms of T^k (weighted by x) are between the two bounds to the power
+    // k, and lambda is the limit of their k-th roots (Gelfand's formula).
+    // We scale x to positive integers and evaluate these ratios exactly,
+    // so the only rounding is in the final conversion to f64, which is
+    // rounded outward.
+    pub fn growth_rate_bounds(&self) -> (f64, f64) {
+        const MAX_ITERS: usize = 100_000;
+        const TOLERANCE: f64 = 1e-15;
+        const SCALE: f64 = (1_u64 << 56) as f64;
+
+        let n = self.num_states();
+        let mut x = vec![1.0; n];
+        for _ in 0..MAX_ITERS {
+            let y = self.apply_f64(&x);
+            let max = y.iter().cloned().fold(0.0, f64::max);
+            let (mut lo, mut hi) = (f64::INFINITY, 0.0_f64);
+            for (&yi, &xi) in y.iter().zip(&x) {
+                lo = lo.min(yi / xi);
+                hi = hi.max(yi / xi);
+            }
+            x = y.iter().map(|&yi| yi / max).collect();
+            if hi - lo <= TOLERANCE * hi {
+                break;
+            }
+        }
+
+        // Exact evaluation of the bounds
+        let xs: Vec<u128> =
+            x.iter().map(|&xi| ((xi * SCALE).ceil() as u128).max(1)).collect();
+        let ys: Vec<u128> = self
+            .transitions
+            .iter()
+            .map(|succs| succs.iter().map(|&(t, m)| m * xs[t]).sum())
+            .collect();
+        let mut lo = (ys[0], xs[0]);
+        let mut hi = (ys[0], xs[0]);
+        for (&yi, &xi) in ys.iter().zip(&xs) {
+            if yi * lo.1 < lo.0 * xi {
+                lo = (yi, xi);
+            }
+            if yi * hi.1 > hi.0 * xi {
+                hi = (yi, xi);
+            }
+        }
+
+        // Each of the two conversions and the division has relative error
+        // at most EPSILON / 2, so widening by 4 * EPSILON is enough.
+        let lo = (lo.0 as f64) / (lo.1 as f64) * (1.0 - 4.0 * f64::EPSILON);
+        let hi = (hi.0 as f64) / (hi.1 as f64) * (1.0 + 4.0 * f64::EPSILON);
+        (lo.next_down(), hi.next_up())
+    }
+}
+
+/*
+    Unit Tests
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_growth_rate_small() {
+        // A 1 x cols grid is friendly only if all blue
+        let (lo, hi) = TransferMatrix::new(1).growth_rate_bounds();
+        assert!(lo <= 1.0 && 1.0 <= hi);
+        // For 2 rows, the count satisfies a(n) = 2 a(n - 1) + a(n - 2)
+        let (lo, hi) = TransferMatrix::new(2).growth_rate_bounds();
+        let root = 1.0 + 2.0_f64.sqrt();
+        assert!(lo <= root && root <= hi);
+    }
+
+    #[test]
+    fn test_growth_rate_vs_counts() {
+        for rows in 1..=6 {
+            let matrix = TransferMatrix::new(rows);
+            let (lo, hi) = matrix.growth_rate_bounds();
+            assert!(lo <= hi && hi - lo <= 1e-12 * hi);
+            // The ratio of consecutive counts tends to the growth rate; use
+            // as many columns as fit in a u128
+            let cols = 127 / rows;
+            let ratio = matrix.count_friendly(cols) as f64
+                / matrix.count_friendly(cols - 1) as f64;
+            assert!(lo * (1.0 - 1e-6) <= ratio && ratio <= hi * (1.0 + 1e-6));
+        }
+    }
+}
@@ -7,6 +7,10 @@
 */
 
 use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::thread;
 use std::vec::Vec;
 
 /*
@@ -14,13 +18,12 @@ use std::vec::Vec;
 
     Elements are integers between 0 and size - 1.
     As posets are expected to be quite small for this problem,
-    this is naively implemented using a single 2D array for the entire
-    set of edges, storing the entire relation u <= v for all pairs u, v.
-    Additionally, there is a compile-time size cap on all posets:
-    MAX_POSET_SIZE.
-    (Without const generics we unfortunately can't make the poset size
-    a type parameter.)
-    We store both forward and backward edges (two 2D arrays).
+    this is naively implemented by storing the entire relation u <= v for
+    all pairs u, v: for each element, its up-set and its down-set as a
+    row of a bit matrix. The matrices are sized dynamically, so there is no
+    cap on the size of a poset (in particular, candidate universal posets
+    can have more than 20 elements), and most operations work a 64-bit word
+    at a time.
 
     Posets additionally store an additional list of levels, where for each i,
     the elements at "level" i are defined to be the minimal elements that
@@ -34,19 +37,110 @@ use std::vec::Vec;
 
 mod poset {
     use std::fmt;
+    use std::str::FromStr;
 
-    const MAX_POSET_SIZE: usize = 20;
+    /*
+        Square matrix of bits, stored row by row, with each row padded to a
+        whole number of 64-bit words (so that rows can be combined a word at
+        a time). All rows are stored in a single Vec.
+    */
+    const WORD_BITS: usize = 64;
+
+    #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct BitMatrix {
+        size: usize,
+        row_words: usize,
+        words: Vec<u64>,
+    }
+    impl BitMatrix {
+        pub fn new(size: usize) -> Self {
+            let row_words = size.div_ceil(WORD_BITS);
+            Self { size, row_words, words: vec![0; size * row_words] }
+        }
+        pub fn resize(&mut self, size: usize) {
+            let mut result = Self::new(size);
+            for u in 0..self.size.min(size) {
+                let old = self.row(u);
+                result.row_mut(u)[..old.len()].copy_from_slice(old);
+            }
+            *self = result;
+        }
+        pub fn row(&self, u: usize) -> &[u64] {
+            &self.words[(u * self.row_words)..((u + 1) * self.row_words)]
+        }
+        fn row_mut(&mut self, u: usize) -> &mut [u64] {
+            &mut self.words[(u * self.row_words)..((u + 1) * self.row_words)]
+        }
+        pub fn contains(&self, u: usize, v: usize) -> bool {
+            self.words[u * self.row_words + v / WORD_BITS]
+                & (1 << (v % WORD_BITS))
+                != 0
+        }
+        // Returns true if (u, v) was not already present
+        pub fn insert(&mut self, u: usize, v: usize) -> bool {
+            let word = &mut self.words[u * self.row_words + v / WORD_BITS];
+            let bit = 1 << (v % WORD_BITS);
+            let added = *word & bit == 0;
+            *word |= bit;
+            added
+        }
+        pub fn row_count(&self, u: usize) -> usize {
+            self.row(u).iter().map(|w| w.count_ones() as usize).sum()
+        }
+        // Add the bits of other to row u; returns the number of new bits
+        pub fn union_row_with(&mut self, u: usize, other: &[u64]) -> usize {
+            let mut added = 0;
+            for (w, &o) in self.row_mut(u).iter_mut().zip(other) {
+                added += (o & !*w).count_ones() as usize;
+                *w |= o;
+            }
+            added
+        }
+        pub fn iter_row(&self, u: usize) -> impl Iterator<Item = usize> + '_ {
+            iter_bits(self.row(u))
+        }
+    }
+
+    // Positions of the bits set in a row
+    fn iter_bits(row: &[u64]) -> impl Iterator<Item = usize> + '_ {
+        row.iter().enumerate().flat_map(|(k, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    None
+                } else {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some(k * WORD_BITS + bit)
+                }
+            })
+        })
+    }
+
+    // The row (of row_words words) with bit f[v] set for each v in bits
+    fn map_bits(
+        bits: impl Iterator<Item = usize>,
+        f: &[usize],
+        row_words: usize,
+    ) -> Vec<u64> {
+        let mut result = vec![0; row_words];
+        for v in bits {
+            result[f[v] / WORD_BITS] |= 1 << (f[v] % WORD_BITS);
+        }
+        result
+    }
 
     #[derive(Clone, Eq, PartialEq)]
     pub struct Poset {
         size: usize,
         num_edges: usize,
-        fwd_edges: [[bool; MAX_POSET_SIZE]; MAX_POSET_SIZE],
-        bck_edges: [[bool; MAX_POSET_SIZE]; MAX_POSET_SIZE],
+        // Row u: up-set { v | u <= v }, and down-set { v | v <= u }
+        up_sets: BitMatrix,
+        down_sets: BitMatrix,
         // Elements sorted into levels for faster isomorphism checking
         num_levels: usize,
-        elem_levels: [usize; MAX_POSET_SIZE], // element -> level
-        level_elems: [Vec<usize>; MAX_POSET_SIZE], // level -> elements
+        elem_levels: Vec<usize>,      // element -> level
+        level_elems: Vec<Vec<usize>>, // level -> elements (num_levels)
     }
     impl fmt::Debug for Poset {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -59,6 +153,56 @@ mod poset {
             write!(f, " }}")
         }
     }
+    // Why an edge list is not a DAG: the edge (u, v) has an element out of
+    // range, or closes a cycle
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum DagError {
+        OutOfRange(usize, usize),
+        Cycle(usize, usize),
+    }
+    impl fmt::Display for DagError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::OutOfRange(u, v) => {
+                    write!(f, "Element out of range in {}<{}", u, v)
+                }
+                Self::Cycle(u, v) => write!(f, "Cycle at {}<{}", u, v),
+            }
+        }
+    }
+
+    // Text format: the size, then the cover relations u < v, e.g.
+    // "3: 0<1 0<2" (see Hasse diagrams, below). Parsing also accepts any
+    // other relations, and takes the transitive closure.
+    impl fmt::Display for Poset {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}:", self.size)?;
+            for (u, v) in self.covers() {
+                write!(f, " {}<{}", u, v)?;
+            }
+            Ok(())
+        }
+    }
+    impl FromStr for Poset {
+        type Err = String;
+        fn from_str(s: &str) -> Result<Self, String> {
+            let parse = |x: &str| {
+                x.trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Bad number {:?}", x))
+            };
+            let (size, relations) =
+                s.split_once(':').ok_or("Expected size:relations")?;
+            let mut edges = Vec::new();
+            for relation in relations.split_whitespace() {
+                let (u, v) = relation.split_once('<').ok_or_else(|| {
+                    format!("Expected u<v, got {:?}", relation)
+                })?;
+                edges.push((parse(u)?, parse(v)?));
+            }
+            Self::from_dag(parse(size)?, &edges).map_err(|e| e.to_string())
+        }
+    }
     impl Poset {
         /* Getters */
         pub fn get_size(&self) -> usize {
@@ -68,25 +212,16 @@ mod poset {
             self.num_edges
         }
         pub fn contains_edge(&self, u: usize, v: usize) -> bool {
-            self.fwd_edges[u][v]
+            self.up_sets.contains(u, v)
         }
-        pub fn sources(&self, v: usize) -> Vec<usize> {
-            let mut results = Vec::new();
-            for u in 0..self.size {
-                if self.bck_edges[v][u] {
-                    results.push(u);
-                }
-            }
-            results
+        pub fn get_up_sets(&self) -> &BitMatrix {
+            &self.up_sets
         }
-        pub fn targets(&self, u: usize) -> Vec<usize> {
-            let mut results = Vec::new();
-            for v in 0..self.size {
-                if self.fwd_edges[u][v] {
-                    results.push(v);
-                }
-            }
-            results
+        pub fn sources(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+            self.down_sets.iter_row(v)
+        }
+        pub fn targets(&self, u: usize) -> impl Iterator<Item = usize> + '_ {
+            self.up_sets.iter_row(u)
         }
         pub fn get_num_levels(&self) -> usize {
             self.num_levels
@@ -110,32 +245,29 @@ mod poset {
 
         /* Object Invariant */
         fn assert_invariant_core(&self) -> bool {
-            // Check size is OK
-            assert!(self.size <= MAX_POSET_SIZE);
-            // Check elements are in range
-            for u in self.size..MAX_POSET_SIZE {
-                for v in 0..MAX_POSET_SIZE {
-                    assert!(!self.fwd_edges[u][v]);
-                    assert!(!self.fwd_edges[v][u]);
-                    assert!(!self.bck_edges[u][v]);
-                    assert!(!self.bck_edges[v][u]);
-                }
-            }
-            // Check fwd/bck edges correspond
-            for u in 0..self.size {
-                for v in 0..self.size {
-                    assert_eq!(self.fwd_edges[u][v], self.bck_edges[v][u]);
+            // Check bitset sizes are OK and elements are in range
+            for sets in [&self.up_sets, &self.down_sets] {
+                assert_eq!(sets.size, self.size);
+                assert_eq!(sets.row_words, self.size.div_ceil(WORD_BITS));
+                assert_eq!(sets.words.len(), self.size * sets.row_words);
+                for u in 0..self.size {
+                    assert!(sets.iter_row(u).all(|v| v < self.size));
                 }
             }
-            // Check # of edges
-            let mut total = 0;
+            assert_eq!(self.elem_levels.len(), self.size);
+            assert_eq!(self.level_elems.len(), self.num_levels);
+            // Check up/down sets correspond
             for u in 0..self.size {
                 for v in 0..self.size {
-                    if self.fwd_edges[u][v] {
-                        total += 1;
-                    }
+                    assert_eq!(
+                        self.up_sets.contains(u, v),
+                        self.down_sets.contains(v, u)
+                    );
                 }
             }
+            // Check # of edges
+            let total: usize =
+                (0..self.size).map(|u| self.up_sets.row_count(u)).sum();
             assert_eq!(total, self.num_edges);
             // Check edges satisfy reflexivity
             for u in 0..self.size {
@@ -155,25 +287,20 @@ mod poset {
                 assert!(self.level_elems[level].contains(&u));
             }
             assert_eq!(
-                (0..MAX_POSET_SIZE)
+                (0..self.num_levels)
                     .map(|k| self.get_level_size(k))
                     .sum::<usize>(),
                 self.get_size()
             );
-            for k in 0..MAX_POSET_SIZE {
-                if k + 1 == self.num_levels {
-                    assert!(self.get_level_size(k) > 0);
-                } else if k >= self.num_levels {
-                    assert!(self.get_level_size(k) == 0);
-                }
+            if self.num_levels > 0 {
+                assert!(self.get_level_size(self.num_levels - 1) > 0);
             }
             // Check that level assignments are correct
             for u in 0..self.size {
                 let max_level: Option<usize> = self
                     .sources(u)
-                    .iter()
-                    .filter(|&&s| s != u)
-                    .map(|&s| self.get_elem_level(s))
+                    .filter(|&s| s != u)
+                    .map(|s| self.get_elem_level(s))
                     .max();
                 let expected_level: usize = match max_level {
                     Some(k) => k + 1,
@@ -193,14 +320,16 @@ mod poset {
         pub fn new_unordered(size: usize) -> Self {
             let mut result = Self {
                 size,
-                fwd_edges: [[false; MAX_POSET_SIZE]; MAX_POSET_SIZE],
-                bck_edges: [[false; MAX_POSET_SIZE]; MAX_POSET_SIZE],
+                up_sets: BitMatrix::new(size),
+                down_sets: BitMatrix::new(size),
                 num_edges: 0,
                 num_levels: if size > 0 { 1 } else { 0 },
-                elem_levels: [0; MAX_POSET_SIZE],
-                level_elems: Default::default(),
+                elem_levels: vec![0; size],
+                level_elems: Vec::new(),
             };
-            result.level_elems[0] = (0..size).collect();
+            if size > 0 {
+                result.level_elems.push((0..size).collect());
+            }
             for e in 0..size {
                 result.add_edge_core(e, e);
             }
@@ -210,29 +339,46 @@ mod poset {
         pub fn new_empty() -> Self {
             Self::new_unordered(0)
         }
+        // The transitive closure of a DAG on 0..size, given by its edges
+        // (u, v), each meaning u < v. Unlike add_edge, returns an error on
+        // cycles.
+        pub fn from_dag(
+            size: usize,
+            edges: &[(usize, usize)],
+        ) -> Result<Self, DagError> {
+            let mut result = Self::new_unordered(size);
+            for &(u, v) in edges {
+                if u >= size || v >= size {
+                    return Err(DagError::OutOfRange(u, v));
+                }
+                if result.contains_edge(v, u) {
+                    return Err(DagError::Cycle(u, v));
+                }
+                if !result.contains_edge(u, v) {
+                    result.add_edge(u, v);
+                }
+            }
+            Ok(result)
+        }
 
         /* Primitive modifiers: these do NOT preserve the invariant */
         fn increase_size_by_core(&mut self, size: usize) {
             self.size += size;
-            if self.size > MAX_POSET_SIZE {
-                panic!(
-                    "Attempted to create poset with too many elements: {} (max {})",
-                    self.size,
-                    MAX_POSET_SIZE,
-                );
-            }
+            self.up_sets.resize(self.size);
+            self.down_sets.resize(self.size);
+            self.elem_levels.resize(self.size, 0);
         }
         fn add_edge_core(&mut self, e1: usize, e2: usize) {
-            if !self.fwd_edges[e1][e2] {
-                self.fwd_edges[e1][e2] = true;
-                self.bck_edges[e2][e1] = true;
+            if self.up_sets.insert(e1, e2) {
+                self.down_sets.insert(e2, e1);
                 self.num_edges += 1;
             }
         }
         fn ensure_level_core(&mut self, k: usize) {
-            debug_assert!(k < MAX_POSET_SIZE);
+            debug_assert!(k < self.size);
             if self.num_levels <= k {
                 self.num_levels = k + 1;
+                self.level_elems.resize(self.num_levels, Vec::new());
             }
         }
         fn remove_elem_level_core(&mut self, e: usize) {
@@ -259,7 +405,8 @@ mod poset {
         fn increase_level_rec(&mut self, e: usize, k: usize) {
             if k > self.get_elem_level(e) {
                 self.change_level_core(e, k);
-                for f in self.targets(e) {
+                let targets: Vec<usize> = self.targets(e).collect();
+                for f in targets {
                     if f != e {
                         self.increase_level_rec(f, k + 1);
                     }
@@ -283,50 +430,155 @@ mod poset {
             assert!(!self.contains_edge(e2, e1));
             // Increase level if necessary
             self.increase_level_rec(e2, self.get_elem_level(e1) + 1);
-            // Add edges for transitive closure
+            // Add edges for transitive closure: everything below e1 is now
+            // below everything above e2
             // (Note that the following includes self.add_edge_core(e1, e2))
-            for e0 in self.sources(e1) {
-                for e3 in self.targets(e2) {
-                    self.add_edge_core(e0, e3);
-                }
+            let below: Vec<u64> = self.down_sets.row(e1).to_vec();
+            let above: Vec<u64> = self.up_sets.row(e2).to_vec();
+            let below_elems: Vec<usize> = self.sources(e1).collect();
+            let above_elems: Vec<usize> = self.targets(e2).collect();
+            for e0 in below_elems {
+                self.num_edges += self.up_sets.union_row_with(e0, &above);
+            }
+            for e3 in above_elems {
+                self.down_sets.union_row_with(e3, &below);
             }
             // Done
             self.assert_invariant();
         }
+        // The poset without element e (later elements move down by one)
+        pub fn without_elem(&self, e: usize) -> Self {
+            let mut result = Self::new_unordered(self.size - 1);
+            let relabel = |u: usize| if u > e { u - 1 } else { u };
+            for u in (0..self.size).filter(|&u| u != e) {
+                for v in self.targets(u).filter(|&v| v != e && v != u) {
+                    if !result.contains_edge(relabel(u), relabel(v)) {
+                        result.add_edge(relabel(u), relabel(v));
+                    }
+                }
+            }
+            result
+        }
         // Disjoint union of two posets
         #[allow(dead_code)]
         pub fn union(&mut self, other: &Self) {
+            // Elements of other come after those of self
+            let offset = self.size;
             self.increase_size_by(other.size);
             for e1 in 0..other.size {
-                for e2 in other.targets(e1) {
-                    self.add_edge(self.size + e1, self.size + e2);
+                // Targets include e1 itself (see increase_size_by)
+                for e2 in other.targets(e1).filter(|&e2| e2 != e1) {
+                    self.add_edge(offset + e1, offset + e2);
                 }
             }
             self.assert_invariant();
         }
         // Check if one poset contains another
-        fn is_embedding(&self, other: &Self, inj: &[usize]) -> bool {
-            for e1 in 0..(self.size) {
-                for f1 in 0..(self.size) {
-                    let e2 = inj[e1];
-                    let f2 = inj[f1];
-                    if self.contains_edge(e1, f1) != other.contains_edge(e2, f2)
-                    {
-                        return false;
-                    }
-                }
+        pub fn embeds_in(&self, other: &Self) -> bool {
+            self.find_embedding(other).is_some()
+        }
+        // Find an embedding into other, if there is one: the element of
+        // other that each element of self maps to.
+        // This is a backtracking search that maps one element at a time, in
+        // order of level, and checks each choice against the elements
+        // already mapped. An element can only map to an element of other
+        // at least as high a level, with at least as many elements above and
+        // below it.
+        pub fn find_embedding(&self, other: &Self) -> Option<Vec<usize>> {
+            let fits = |e1: usize, f1: usize| {
+                self.get_elem_level(e1) <= other.get_elem_level(f1)
+                    && self.up_sets.row_count(e1) <= other.up_sets.row_count(f1)
+                    && self.down_sets.row_count(e1)
+                        <= other.down_sets.row_count(f1)
+            };
+            let order: Vec<usize> =
+                self.level_elems.iter().flatten().copied().collect();
+            let candidates: Vec<Vec<usize>> = order
+                .iter()
+                .map(|&e1| (0..other.size).filter(|&f1| fits(e1, f1)).collect())
+                .collect();
+            let mut emb = vec![0; self.size];
+            let mut mapped = vec![0; self.size.div_ceil(WORD_BITS)];
+            let mut image = vec![0; other.size.div_ceil(WORD_BITS)];
+            if self.extend_embedding(
+                other,
+                &order,
+                &candidates,
+                &mut emb,
+                &mut mapped,
+                &mut image,
+                0,
+            ) {
+                Some(emb)
+            } else {
+                None
             }
-            true
         }
-        pub fn embeds_in(&self, other: &Self) -> bool {
-            use crate::enumerate_injections;
-            let mut injections = enumerate_injections(self.size, other.size);
-            for inj in injections.drain(..) {
-                if self.is_embedding(other, &inj) {
-                    return true;
+        // Map order[depth..], given that order[..depth] is mapped by emb.
+        // mapped is the set of elements of self mapped so far, and image the
+        // set of elements of other they map to (as rows of bits).
+        // Choosing f1 for e1 is consistent when the up-set and down-set of
+        // f1, restricted to the image, are those of e1 (restricted to the
+        // elements mapped so far) mapped by emb. These only depend on e1, so
+        // each candidate is checked a word at a time.
+        #[allow(clippy::too_many_arguments)]
+        fn extend_embedding(
+            &self,
+            other: &Self,
+            order: &[usize],
+            candidates: &[Vec<usize>],
+            emb: &mut Vec<usize>,
+            mapped: &mut Vec<u64>,
+            image: &mut Vec<u64>,
+            depth: usize,
+        ) -> bool {
+            if depth == order.len() {
+                return true;
+            }
+            let e1 = order[depth];
+            let map_mapped = |row: &[u64]| {
+                let masked: Vec<u64> = row
+                    .iter()
+                    .zip(mapped.iter())
+                    .map(|(&r, &m)| r & m)
+                    .collect();
+                map_bits(iter_bits(&masked), emb, image.len())
+            };
+            let up = map_mapped(self.up_sets.row(e1));
+            let down = map_mapped(self.down_sets.row(e1));
+            let (w1, b1) = (e1 / WORD_BITS, 1 << (e1 % WORD_BITS));
+            for &f1 in &candidates[depth] {
+                let (w2, b2) = (f1 / WORD_BITS, 1 << (f1 % WORD_BITS));
+                if image[w2] & b2 != 0 {
+                    continue;
+                }
+                let restricted_eq = |row: &[u64], expected: &[u64]| {
+                    row.iter()
+                        .zip(image.iter())
+                        .zip(expected)
+                        .all(|((&r, &m), &e)| r & m == e)
+                };
+                if restricted_eq(other.up_sets.row(f1), &up)
+                    && restricted_eq(other.down_sets.row(f1), &down)
+                {
+                    emb[e1] = f1;
+                    mapped[w1] |= b1;
+                    image[w2] |= b2;
+                    if self.extend_embedding(
+                        other,
+                        order,
+                        candidates,
+                        emb,
+                        mapped,
+                        image,
+                        depth + 1,
+                    ) {
+                        return true;
+                    }
+                    mapped[w1] &= !b1;
+                    image[w2] &= !b2;
                 }
             }
-            // No injection works
             false
         }
         // Check if two posets are isomorphic
@@ -338,29 +590,30 @@ mod poset {
         ) -> bool {
             debug_assert_eq!(self.get_level_sizes(), level_sizes);
             debug_assert_eq!(other.get_level_sizes(), level_sizes);
-            for level_e in 0..self.num_levels {
-                for level_f in (level_e + 1)..self.num_levels {
-                    let level_size_e = level_sizes[level_e];
-                    let level_size_f = level_sizes[level_f];
-                    for i1 in 0..level_size_e {
-                        for j1 in 0..level_size_f {
-                            let i2 = bij_list[level_e][i1];
-                            let j2 = bij_list[level_f][j1];
-                            let e1 = self.level_elems[level_e][i1];
-                            let f1 = self.level_elems[level_f][j1];
-                            let e2 = other.level_elems[level_e][i2];
-                            let f2 = other.level_elems[level_f][j2];
-                            if self.contains_edge(e1, f1)
-                                != other.contains_edge(e2, f2)
-                            {
-                                return false;
-                            }
-                        }
-                    }
+            // The element of other that each element of self maps to
+            let mut perm = vec![0; self.size];
+            for (k, bij) in bij_list.iter().enumerate() {
+                for (i1, &i2) in bij.iter().enumerate() {
+                    perm[self.level_elems[k][i1]] = other.level_elems[k][i2];
                 }
             }
-            true
+            self.permutes_to(other, &perm)
+        }
+        // Check a bijection perm is an isomorphism: for each element e1, the
+        // up-set of e1 mapped by perm should be the up-set of perm[e1] in
+        // other. Since the bottleneck is when the check succeeds, this maps
+        // only the bits that are set, and compares the rows a word at a time.
+        fn permutes_to(&self, other: &Self, perm: &[usize]) -> bool {
+            let row_words = other.up_sets.row_words;
+            perm.iter().enumerate().all(|(e1, &e2)| {
+                let mapped =
+                    map_bits(self.up_sets.iter_row(e1), perm, row_words);
+                mapped == other.up_sets.row(e2)
+            })
         }
+        // Enumerating posets now uses canonical_form instead, but this is
+        // kept as an independent check
+        #[allow(dead_code)]
         pub fn isomorphic(&self, other: &Self) -> bool {
             use crate::enumerate_bijection_lists;
             if self.get_size() == other.get_size()
@@ -369,9 +622,7 @@ mod poset {
                 && self.get_level_sizes() == other.get_level_sizes()
             {
                 let level_sizes = self.get_level_sizes();
-                let mut bijection_lists =
-                    enumerate_bijection_lists(level_sizes.clone());
-                for bij_list in bijection_lists.drain(..) {
+                for bij_list in enumerate_bijection_lists(level_sizes.clone()) {
                     if self.is_leveled_isomorphism(
                         other,
                         &level_sizes,
@@ -383,6 +634,199 @@ mod poset {
             };
             false
         }
+
+        /* Hasse diagrams */
+        // Check if v covers u: u < v with nothing in between
+        pub fn is_cover(&self, u: usize, v: usize) -> bool {
+            u != v
+                && self.contains_edge(u, v)
+                && !self
+                    .targets(u)
+                    .any(|w| w != u && w != v && self.contains_edge(w, v))
+        }
+        // Elements covering u
+        pub fn upper_covers(
+            &self,
+            u: usize,
+        ) -> impl Iterator<Item = usize> + '_ {
+            self.targets(u).filter(move |&v| self.is_cover(u, v))
+        }
+        // Elements covered by v
+        #[allow(dead_code)]
+        pub fn lower_covers(
+            &self,
+            v: usize,
+        ) -> impl Iterator<Item = usize> + '_ {
+            self.sources(v).filter(move |&u| self.is_cover(u, v))
+        }
+        // All cover relations (u, v), where v covers u: the transitive
+        // reduction of the order
+        pub fn covers(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+            (0..self.size)
+                .flat_map(move |u| self.upper_covers(u).map(move |v| (u, v)))
+        }
+        #[allow(dead_code)]
+        pub fn get_num_covers(&self) -> usize {
+            self.covers().count()
+        }
+        // Graphviz DOT for the Hasse diagram (cover relations only), drawn
+        // bottom to top with the elements of each level side by side
+        pub fn to_dot(&self) -> String {
+            let mut result = String::from("digraph poset {\n    rankdir=BT;\n");
+            for k in 0..self.num_levels {
+                result.push_str("    { rank=same;");
+                for &u in self.get_level_elems(k) {
+                    result.push_str(&format!(" {};", u));
+                }
+                result.push_str(" }\n");
+            }
+            for (u, v) in self.covers() {
+                result.push_str(&format!("    {} -> {};\n", u, v));
+            }
+            result.push_str("}\n");
+            result
+        }
+
+        /* Chains and antichains */
+        // Length of the longest chain
+        pub fn height(&self) -> usize {
+            self.num_levels
+        }
+        // Size of the largest antichain. By Dilworth's theorem, this is the
+        // least number of chains covering the poset, which is the size minus
+        // a maximum matching between u and v with u < v.
+        pub fn width(&self) -> usize {
+            // The element matched below each element
+            let mut matched_below: Vec<Option<usize>> = vec![None; self.size];
+            let mut matching = 0;
+            for u in 0..self.size {
+                let mut visited = vec![false; self.size];
+                if self.augment(u, &mut matched_below, &mut visited) {
+                    matching += 1;
+                }
+            }
+            self.size - matching
+        }
+        // Try to match u below some element, rematching others if needed
+        fn augment(
+            &self,
+            u: usize,
+            matched_below: &mut Vec<Option<usize>>,
+            visited: &mut Vec<bool>,
+        ) -> bool {
+            for v in self.targets(u).filter(|&v| v != u) {
+                if !visited[v] {
+                    visited[v] = true;
+                    let free = match matched_below[v] {
+                        None => true,
+                        Some(w) => self.augment(w, matched_below, visited),
+                    };
+                    if free {
+                        matched_below[v] = Some(u);
+                        return true;
+                    }
+                }
+            }
+            false
+        }
+        // Check if no two of the elements are comparable
+        pub fn is_antichain(&self, elems: &[usize]) -> bool {
+            elems.iter().all(|&u| {
+                elems.iter().all(|&v| u == v || !self.contains_edge(u, v))
+            })
+        }
+
+        /* Canonical forms */
+        // Color the elements so that isomorphisms (preserving the initial
+        // colors) preserve colors: start from the initial colors, the level
+        // and the up-set and down-set sizes, then refine by the colors above
+        // and below each element until no class splits.
+        // Colors are 0 through (number of colors - 1).
+        fn refined_colors(&self, initial: &[usize]) -> Vec<usize> {
+            let mut colors: Vec<usize> = initial.to_vec();
+            let mut num_colors = {
+                let mut distinct = colors.clone();
+                distinct.sort_unstable();
+                distinct.dedup();
+                distinct.len()
+            };
+            loop {
+                let signatures: Vec<Vec<usize>> = (0..self.size)
+                    .map(|u| {
+                        let mut above: Vec<usize> =
+                            self.targets(u).map(|v| colors[v]).collect();
+                        let mut below: Vec<usize> =
+                            self.sources(u).map(|v| colors[v]).collect();
+                        above.sort_unstable();
+                        below.sort_unstable();
+                        let mut sig = vec![
+                            colors[u],
+                            self.get_elem_level(u),
+                            above.len(),
+                            below.len(),
+                        ];
+                        sig.append(&mut above);
+                        sig.append(&mut below);
+                        sig
+                    })
+                    .collect();
+                let mut distinct = signatures.clone();
+                distinct.sort_unstable();
+                distinct.dedup();
+                for u in 0..self.size {
+                    colors[u] = distinct.binary_search(&signatures[u]).unwrap();
+                }
+                if distinct.len() == num_colors {
+                    return colors;
+                }
+                num_colors = distinct.len();
+            }
+        }
+        // Canonical form: two posets are isomorphic iff their canonical
+        // forms are equal. This is the lexicographically least relation
+        // matrix over all relabellings that list the elements in order of
+        // color (see refined_colors), so only relabellings within each color
+        // class need to be tried.
+        pub fn canonical_form(&self) -> BitMatrix {
+            self.canonical_labelling(&vec![0; self.size]).0
+        }
+        // Canonical form for posets with colored elements (isomorphisms must
+        // preserve colors), and the new label of each element
+        pub fn canonical_labelling(
+            &self,
+            initial: &[usize],
+        ) -> (BitMatrix, Vec<usize>) {
+            use crate::enumerate_bijection_lists;
+            let colors = self.refined_colors(initial);
+            let num_colors = colors.iter().map(|&c| c + 1).max().unwrap_or(0);
+            let mut classes: Vec<Vec<usize>> = vec![Vec::new(); num_colors];
+            for (u, &c) in colors.iter().enumerate() {
+                classes[c].push(u);
+            }
+            let class_sizes = classes.iter().map(Vec::len).collect();
+            let mut best: Option<(BitMatrix, Vec<usize>)> = None;
+            for bij_list in enumerate_bijection_lists(class_sizes) {
+                // The new label of each element
+                let mut label = vec![0; self.size];
+                let mut next = 0;
+                for (class, bij) in classes.iter().zip(&bij_list) {
+                    for (i, &u) in class.iter().enumerate() {
+                        label[u] = next + bij[i];
+                    }
+                    next += class.len();
+                }
+                let mut matrix = BitMatrix::new(self.size);
+                for u in 0..self.size {
+                    for v in self.targets(u) {
+                        matrix.insert(label[u], label[v]);
+                    }
+                }
+                if best.as_ref().is_none_or(|(b, _)| matrix < *b) {
+                    best = Some((matrix, label));
+                }
+            }
+            best.unwrap()
+        }
     }
 }
 
@@ -390,51 +834,51 @@ use poset::Poset;
 
 /*
     Some useful utility enumerators, before we enumerate posets
+
+    These are lazy: each is an iterator that computes one item at a time,
+    so callers that stop early (or only look at one item at a time) don't
+    pay for the whole, possibly huge, list.
 */
 
+type Enumeration<T> = Box<dyn Iterator<Item = T>>;
+
 // Enumerate all subsets of 0..size
-fn enumerate_subsets(size: usize) -> Vec<Vec<usize>> {
+fn enumerate_subsets(size: usize) -> Enumeration<Vec<usize>> {
     if size == 0 {
-        vec![Vec::new()]
+        Box::new(std::iter::once(Vec::new()))
     } else {
-        let mut results = Vec::new();
-        for mut subset in enumerate_subsets(size - 1).drain(..) {
-            results.push(subset.clone());
-            subset.push(size - 1);
-            results.push(subset);
-        }
-        results
+        Box::new(enumerate_subsets(size - 1).flat_map(move |subset| {
+            let mut with_last = subset.clone();
+            with_last.push(size - 1);
+            [subset, with_last]
+        }))
     }
 }
 
 // Enumerate all partitions of size into parts parts
-fn enumerate_partitions(size: usize) -> Vec<Vec<usize>> {
+fn enumerate_partitions(size: usize) -> Enumeration<Vec<usize>> {
     if size == 0 {
-        vec![Vec::new()]
+        Box::new(std::iter::once(Vec::new()))
     } else {
-        let mut results = Vec::new();
-        for last_ele in 1..(size + 1) {
-            for mut subset in enumerate_partitions(size - last_ele).drain(..) {
+        Box::new((1..(size + 1)).flat_map(move |last_ele| {
+            enumerate_partitions(size - last_ele).map(move |mut subset| {
                 subset.push(last_ele);
-                results.push(subset);
-            }
-        }
-        results
+                subset
+            })
+        }))
     }
 }
 
 // Enumerate all injections from 0..isize -> 0..osize
-fn enumerate_injections(isize: usize, osize: usize) -> Vec<Vec<usize>> {
+fn enumerate_injections(isize: usize, osize: usize) -> Enumeration<Vec<usize>> {
     if isize == 0 {
-        return vec![vec![]];
+        return Box::new(std::iter::once(vec![]));
     } else if osize == 0 {
-        return vec![];
+        return Box::new(std::iter::empty());
     }
-    let mut results = Vec::new();
-    let smaller_injections = enumerate_injections(isize - 1, osize - 1);
-    for i in 0..osize {
+    Box::new((0..osize).flat_map(move |i| {
         // Element isize-1 maps to i
-        for mut inj in smaller_injections.clone().drain(..) {
+        enumerate_injections(isize - 1, osize - 1).map(move |mut inj| {
             assert_eq!(inj.len(), isize - 1);
             for item in inj.iter_mut() {
                 if *item >= i {
@@ -442,34 +886,31 @@ fn enumerate_injections(isize: usize, osize: usize) -> Vec<Vec<usize>> {
                 }
             }
             inj.push(i);
-            results.push(inj);
-        }
-    }
-    results
+            inj
+        })
+    }))
 }
 // Enumerate all bijections from 0..size -> 0..size
-fn enumerate_bijections(size: usize) -> Vec<Vec<usize>> {
+fn enumerate_bijections(size: usize) -> Enumeration<Vec<usize>> {
     enumerate_injections(size, size)
 }
 // Enumerate all lists of bijections of the given sizes.
 // E.g., on input [3, 2], enumerate all pairs of a bijection of size 3
 // and a bijection of size 2.
-fn enumerate_bijection_lists(mut sizes: Vec<usize>) -> Vec<Vec<Vec<usize>>> {
+fn enumerate_bijection_lists(
+    mut sizes: Vec<usize>,
+) -> Enumeration<Vec<Vec<usize>>> {
     match sizes.pop() {
-        None => vec![vec![]],
-        Some(last_size) => {
-            let sub_bij_lists = enumerate_bijection_lists(sizes);
-            let bijs = enumerate_bijections(last_size);
-            let mut results = Vec::new();
-            for sub_bij_list in &sub_bij_lists {
-                for bij in &bijs {
+        None => Box::new(std::iter::once(vec![])),
+        Some(last_size) => Box::new(enumerate_bijection_lists(sizes).flat_map(
+            move |sub_bij_list| {
+                enumerate_bijections(last_size).map(move |bij| {
                     let mut bij_list = sub_bij_list.clone();
-                    bij_list.push(bij.clone());
-                    results.push(bij_list);
-                }
-            }
-            results
-        }
+                    bij_list.push(bij);
+                    bij_list
+                })
+            },
+        )),
     }
 }
 
@@ -509,17 +950,15 @@ fn enumerate_posets_leveled_rec(
         // least one element in the top level
         let prev_size = total_size - level_sizes[numlevels - 1];
         let prevprev_size = prev_size - level_sizes[numlevels - 2];
-        let mut subsets = enumerate_subsets(prev_size);
-        let mut good_subsets = Vec::new();
-        for subset in subsets.drain(..) {
-            if !subset.is_empty() && subset[subset.len() - 1] >= prevprev_size {
-                good_subsets.push(subset);
-            }
-        }
-        // Recurse to find all smaller posets
+        let good_subsets: Vec<Vec<usize>> = enumerate_subsets(prev_size)
+            .filter(|subset| {
+                !subset.is_empty() && subset[subset.len() - 1] >= prevprev_size
+            })
+            .collect();
+        // Recurse to find all smaller posets, up to isomorphism (adding the
+        // new element to isomorphic posets gives isomorphic results)
         level_sizes[numlevels - 1] -= 1;
-        let mut subposets =
-            enumerate_posets_leveled_rec(level_sizes, total_size - 1);
+        let mut subposets = enumerate_posets_leveled(level_sizes);
         level_sizes[numlevels - 1] += 1;
         // For all prev posets, add elements dependent on the good subsets
         let mut results = Vec::new();
@@ -544,19 +983,19 @@ fn enumerate_posets_leveled_rec(
 fn enumerate_posets_leveled(level_sizes: &mut Vec<usize>) -> Vec<Poset> {
     // println!("enumerate_posets_leveled: {:?}", level_sizes);
     let mut results = Vec::new();
+    let mut seen_exact = HashSet::new();
+    let mut seen = HashSet::new();
     let posets =
         enumerate_posets_leveled_rec(level_sizes, level_sizes.iter().sum());
-    for (i, poset_ref) in posets.iter().enumerate() {
-        // Only add if not isomorphic to any earlier poset
-        let mut isomorphic = false;
-        for other_ref in posets.iter().take(i) {
-            if poset_ref.isomorphic(other_ref) {
-                isomorphic = true;
-                break;
-            }
+    for poset in posets {
+        // Most repeats are equal, not just isomorphic, and those are cheap
+        // to skip before computing the canonical form
+        if !seen_exact.insert(poset.get_up_sets().clone()) {
+            continue;
         }
-        if !isomorphic {
-            results.push(poset_ref.clone());
+        // Only add if not isomorphic to any earlier poset
+        if seen.insert(poset.canonical_form()) {
+            results.push(poset);
         }
     }
     results
@@ -570,7 +1009,7 @@ fn enumerate_posets(size: usize) -> Vec<Poset> {
         vec![Poset::new_empty()]
     } else {
         let mut results = Vec::new();
-        for mut partition in enumerate_partitions(size).drain(..) {
+        for mut partition in enumerate_partitions(size) {
             results.append(&mut enumerate_posets_leveled(&mut partition));
         }
         results
@@ -589,126 +1028,520 @@ fn enumerate_posets(size: usize) -> Vec<Poset> {
     // }
     // result
 }
-// Enumerate all candidates for a universal poset, using a few simple
-// search optimizations.
-// This is a super gnarly function and should be rewritten...
+// Enumerate all candidates for a universal poset: posets with universal_size
+// elements that contain a chain and an antichain of size base_size.
+// The smallest candidate is the base_size-chain, which is returned (alone)
+// at universal_size = base_size, even though it has no large antichain.
+// Guarantee: exactly-once
 fn enumerate_candidate_universal_posets(
     base_size: usize,
     universal_size: usize,
 ) -> Vec<Poset> {
-    // let min_size = if base_size == 0 { 0 } else { base_size * 2 - 1 };
     let min_size = base_size;
+    let mut chain = Poset::new_unordered(min_size);
+    for i in 1..base_size {
+        chain.add_edge(i - 1, i);
+    }
     match universal_size.cmp(&min_size) {
         Ordering::Less => vec![],
-        Ordering::Equal => {
-            let mut universal = Poset::new_unordered(min_size);
-            // 0 through base_size - 1 ordered
-            // base_size through base_size * 2 - 2 unordered
-            for i in 1..base_size {
-                universal.add_edge(i - 1, i);
+        Ordering::Equal => vec![chain],
+        Ordering::Greater => {
+            let mut candidates = vec![chain];
+            for size in (min_size + 1)..=universal_size {
+                // Each new element can add at most one to the width, so
+                // prune posets that can no longer reach width base_size
+                let remaining = universal_size - size;
+                candidates = candidates
+                    .iter()
+                    .flat_map(|poset| extend_candidate(poset, base_size))
+                    .filter(|poset| poset.width() + remaining >= base_size)
+                    .collect();
             }
-            vec![universal]
+            candidates
         }
-        Ordering::Greater => {
-            let mut result = Vec::new();
-            let smaller_posets = enumerate_candidate_universal_posets(
-                base_size,
-                universal_size - 1,
-            );
-            let smaller_subsets = enumerate_subsets(universal_size - 1);
-            for subset1 in smaller_subsets.clone().drain(..) {
-                for subset2 in smaller_subsets.clone().drain(..) {
-                    // If the subsets overlap, skip
-                    let mut overlap = false;
-                    for &ele1 in &subset1 {
-                        for &ele2 in &subset2 {
-                            if ele1 == ele2 {
-                                overlap = true;
-                                break;
-                            }
-                        }
-                        if overlap {
-                            break;
-                        }
-                    }
-                    if overlap {
-                        continue;
-                    }
-                    for mut poset in smaller_posets.clone().drain(..) {
-                        // If the subsets create a cycle, skip
-                        let mut cycle = false;
-                        for &ele1 in &subset1 {
-                            for &ele2 in &subset2 {
-                                if poset.contains_edge(ele2, ele1) {
-                                    cycle = true;
-                                    break;
-                                }
-                            }
-                            if cycle {
-                                break;
-                            }
-                        }
-                        if cycle {
-                            continue;
-                        }
-                        poset.increase_size_by(1);
-                        for ele in subset1.clone().drain(..) {
-                            poset.add_edge(ele, universal_size - 1);
-                        }
-                        for ele in subset2.clone().drain(..) {
-                            poset.add_edge(universal_size - 1, ele);
-                        }
-                        result.push(poset);
-                    }
-                }
+    }
+}
+// Orderly generation (canonical augmentation): extend a candidate by one
+// element in every way, keeping only the extensions where the new element
+// is the canonical one to delete (up to automorphism), and only one of any
+// isomorphic extensions. Then each candidate (up to isomorphism) is
+// generated from exactly one smaller candidate, exactly once, provided the
+// smaller candidates were.
+// Candidates must contain a base_size-chain, so the canonical element to
+// delete is the one with the largest canonical label among those whose
+// deletion leaves such a chain.
+fn extend_candidate(poset: &Poset, base_size: usize) -> Vec<Poset> {
+    let size = poset.get_size();
+    let new = size;
+    let antichains: Vec<Vec<usize>> =
+        enumerate_subsets(size).filter(|s| poset.is_antichain(s)).collect();
+    let mut seen = HashSet::new();
+    let mut results = Vec::new();
+    // The new element is above the antichain below and below the antichain
+    // above, which determine it (given that the poset stays the same without
+    // it)
+    for below in &antichains {
+        for above in &antichains {
+            // Skip if this would add new relations between old elements
+            if !below.iter().all(|&e1| {
+                above.iter().all(|&e2| e1 != e2 && poset.contains_edge(e1, e2))
+            }) {
+                continue;
+            }
+            let mut result = poset.clone();
+            result.increase_size_by(1);
+            for &e in below {
+                result.add_edge(e, new);
+            }
+            for &e in above {
+                result.add_edge(new, e);
+            }
+            let (form, labels) = result.canonical_labelling(&vec![0; size + 1]);
+            let canonical = (0..=size)
+                .filter(|&e| result.without_elem(e).height() >= base_size)
+                .max_by_key(|&e| labels[e])
+                .unwrap();
+            if same_orbit(&result, new, canonical) && seen.insert(form) {
+                results.push(result);
             }
-            result
         }
     }
+    results
+}
+// Check if some automorphism maps e1 to e2: this is when the poset with e1
+// marked is isomorphic to the poset with e2 marked (the marked element is
+// then alone in its color class, so it gets the same label in both).
+fn same_orbit(poset: &Poset, e1: usize, e2: usize) -> bool {
+    let marked = |e: usize| {
+        let mut colors = vec![0; poset.get_size()];
+        colors[e] = 1;
+        let (form, labels) = poset.canonical_labelling(&colors);
+        (form, labels[e])
+    };
+    e1 == e2 || marked(e1) == marked(e2)
 }
 
 /*
     Solve the universal poset problem
 */
-fn solve_universal_poset(base_size: usize) -> usize {
+
+// Number of progress reports per size (each a line, not a \r update, since
+// reports come from several threads)
+const PROGRESS_REPORTS: usize = 10;
+
+// Test the candidates in parallel, and return the index of the first one
+// (in order, as in a sequential search) into which all base posets embed.
+// Workers take candidates in order from a shared counter, and stop once a
+// universal candidate earlier than the next one is found.
+fn find_universal_poset(
+    base_posets: &[Poset],
+    candidates: &[Poset],
+) -> Option<usize> {
+    let num_candidates = candidates.len();
+    let num_threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let next = AtomicUsize::new(0);
+    let found = AtomicUsize::new(usize::MAX);
+    let complete = AtomicUsize::new(0);
+    let report_every = num_candidates.div_ceil(PROGRESS_REPORTS).max(1);
+    thread::scope(|scope| {
+        for _ in 0..num_threads {
+            scope.spawn(|| loop {
+                let num = next.fetch_add(1, AtomicOrdering::SeqCst);
+                if num >= num_candidates
+                    || num > found.load(AtomicOrdering::SeqCst)
+                {
+                    break;
+                }
+                let candidate = &candidates[num];
+                if base_posets.iter().all(|base| base.embeds_in(candidate)) {
+                    found.fetch_min(num, AtomicOrdering::SeqCst);
+                }
+                let done = complete.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+                if done.is_multiple_of(report_every) || done == num_candidates {
+                    println!("  ({} of {} complete)", done, num_candidates);
+                }
+            });
+        }
+    });
+    match found.into_inner() {
+        usize::MAX => None,
+        num => Some(num),
+    }
+}
+
+// Find a smallest poset into which every poset of size base_size embeds
+fn find_smallest_universal_poset(base_size: usize) -> Poset {
     let base_posets = enumerate_posets(base_size);
     // println!("Enumerated {} posets of size {}", base_posets.len(), base_size);
     for universal_size in base_size.. {
         let mut candidates =
             enumerate_candidate_universal_posets(base_size, universal_size);
-        let num_candidates = candidates.len();
-        for (num, candidate) in candidates.drain(..).enumerate() {
-            print!(
-                "\rTesting {} candidates of size {}... ({} complete)",
-                num_candidates, universal_size, num
-            );
-            let mut is_universal = true;
-            for base_poset in &base_posets {
-                if !base_poset.embeds_in(&candidate) {
-                    is_universal = false;
-                    break;
+        println!(
+            "Testing {} candidates of size {}...",
+            candidates.len(),
+            universal_size
+        );
+        if let Some(num) = find_universal_poset(&base_posets, &candidates) {
+            println!("Universal poset found: {}", candidates[num]);
+            return candidates.swap_remove(num);
+        }
+    }
+    unreachable!()
+}
+
+fn solve_universal_poset(base_size: usize) -> usize {
+    find_smallest_universal_poset(base_size).get_size()
+}
+
+/*
+    SAT encoding of the universal poset problem
+
+    To get past what the search above can reach, we can ask a SAT solver
+    whether there is a universal poset of a given size. For base_size n and
+    universal_size u, the formula has:
+    - a variable for each pair a < b (as integers) of universal elements,
+      true if a < b in the universal poset. Every poset has a labelling
+      where the order is contained in the order on integers, so this loses
+      nothing, and antisymmetry is automatic.
+    - for each base poset and each of its elements i, and each universal
+      element a, a variable that is true if i maps to a. Each element maps
+      to exactly one universal element, no two to the same one, and the
+      map preserves and reflects the order.
+
+    The formula is written in DIMACS format for an external solver; the
+    solver's model can then be read back into a Poset and checked with
+    embeds_in. There is also a small DPLL solver for tiny instances.
+*/
+
+mod sat {
+    use crate::enumerate_posets;
+    use crate::poset::Poset;
+    use std::fmt::Write;
+
+    // DIMACS literal: variable v (from 1) is v, its negation is -v
+    pub type Lit = i32;
+
+    #[derive(Clone, Debug, Default)]
+    pub struct Cnf {
+        num_vars: usize,
+        clauses: Vec<Vec<Lit>>,
+    }
+    impl Cnf {
+        pub fn new_var(&mut self) -> Lit {
+            self.num_vars += 1;
+            self.num_vars as Lit
+        }
+        pub fn add_clause(&mut self, clause: Vec<Lit>) {
+            self.clauses.push(clause);
+        }
+        pub fn get_num_vars(&self) -> usize {
+            self.num_vars
+        }
+        pub fn to_dimacs(&self) -> String {
+            let mut result = String::new();
+            writeln!(result, "p cnf {} {}", self.num_vars, self.clauses.len())
+                .unwrap();
+            for clause in &self.clauses {
+                for lit in clause {
+                    write!(result, "{} ", lit).unwrap();
                 }
+                writeln!(result, "0").unwrap();
             }
-            print!(
-                "\rTesting {} candidates of size {}... ({} complete)",
-                num_candidates,
-                universal_size,
-                num + 1
-            );
-            if is_universal {
-                println!("\nUniversal poset found: {:?}", candidate);
-                return universal_size;
+            result
+        }
+        // Check an assignment (value of variable v at index v - 1)
+        pub fn satisfied_by(&self, model: &[bool]) -> bool {
+            self.clauses
+                .iter()
+                .all(|clause| clause.iter().any(|&l| value(model, l)))
+        }
+    }
+    fn value(model: &[bool], lit: Lit) -> bool {
+        model[lit.unsigned_abs() as usize - 1] == (lit > 0)
+    }
+
+    pub struct Encoding {
+        base_size: usize,
+        universal_size: usize,
+        cnf: Cnf,
+        // (a, b) -> variable for a < b, where a < b as integers
+        less: Vec<Vec<Option<Lit>>>,
+    }
+    impl Encoding {
+        #[allow(clippy::needless_range_loop)]
+        pub fn new(base_size: usize, universal_size: usize) -> Self {
+            let u = universal_size;
+            let mut cnf = Cnf::default();
+            let mut less = vec![vec![None; u]; u];
+            for a in 0..u {
+                for b in (a + 1)..u {
+                    less[a][b] = Some(cnf.new_var());
+                }
+            }
+            // Transitivity
+            for a in 0..u {
+                for b in (a + 1)..u {
+                    for c in (b + 1)..u {
+                        let (ab, bc) =
+                            (less[a][b].unwrap(), less[b][c].unwrap());
+                        cnf.add_clause(vec![-ab, -bc, less[a][c].unwrap()]);
+                    }
+                }
+            }
+            // An embedding of each base poset
+            for base in enumerate_posets(base_size) {
+                let n = base.get_size();
+                let maps: Vec<Vec<Lit>> = (0..n)
+                    .map(|_| (0..u).map(|_| cnf.new_var()).collect())
+                    .collect();
+                for i in 0..n {
+                    // Each element maps somewhere, and only to one place
+                    cnf.add_clause(maps[i].clone());
+                    for a in 0..u {
+                        for b in (a + 1)..u {
+                            cnf.add_clause(vec![-maps[i][a], -maps[i][b]]);
+                        }
+                    }
+                    for j in 0..n {
+                        if i == j {
+                            continue;
+                        }
+                        let below = base.contains_edge(i, j);
+                        let above = base.contains_edge(j, i);
+                        for a in 0..u {
+                            for b in 0..u {
+                                let not_both = vec![-maps[i][a], -maps[j][b]];
+                                let clause = match (a == b, less[a][b]) {
+                                    // Injectivity (each pair once)
+                                    (true, _) if i < j => Some(not_both),
+                                    (true, _) => None,
+                                    // i < j needs a < b
+                                    (false, None) if below => Some(not_both),
+                                    (false, Some(ab)) if below => {
+                                        Some([not_both, vec![ab]].concat())
+                                    }
+                                    // Incomparable i, j need a, b incomparable
+                                    (false, Some(ab)) if !above => {
+                                        Some([not_both, vec![-ab]].concat())
+                                    }
+                                    _ => None,
+                                };
+                                if let Some(clause) = clause {
+                                    cnf.add_clause(clause);
+                                }
+                            }
+                        }
+                    }
+                }
             }
+            Self { base_size, universal_size, cnf, less }
+        }
+        pub fn get_cnf(&self) -> &Cnf {
+            &self.cnf
+        }
+        pub fn to_dimacs(&self) -> String {
+            self.cnf.to_dimacs()
+        }
+        // Read the universal poset from a model
+        pub fn decode(&self, model: &[bool]) -> Poset {
+            let mut result = Poset::new_unordered(self.universal_size);
+            for a in 0..self.universal_size {
+                for b in (a + 1)..self.universal_size {
+                    let ab = self.less[a][b].unwrap();
+                    if value(model, ab) && !result.contains_edge(a, b) {
+                        result.add_edge(a, b);
+                    }
+                }
+            }
+            result
+        }
+        // Check that all base posets embed in the poset
+        pub fn verify(&self, poset: &Poset) -> bool {
+            poset.get_size() == self.universal_size
+                && enumerate_posets(self.base_size)
+                    .iter()
+                    .all(|base| base.embeds_in(poset))
+        }
+    }
+
+    // Parse a solver's output in the usual format: "s SATISFIABLE" or
+    // "s UNSATISFIABLE", then "v" lines listing literals, ending with 0.
+    // Returns the model (None if unsatisfiable) as the value of each
+    // variable.
+    pub fn parse_model(
+        text: &str,
+        num_vars: usize,
+    ) -> Result<Option<Vec<bool>>, String> {
+        let mut model = vec![false; num_vars];
+        let mut status = None;
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("s") => status = Some(words.collect::<Vec<_>>().join(" ")),
+                Some("v") => {
+                    for word in words {
+                        let lit: Lit = word
+                            .parse()
+                            .map_err(|_| format!("Bad literal {:?}", word))?;
+                        let var = lit.unsigned_abs() as usize;
+                        if var > num_vars {
+                            return Err(format!("Unknown variable {}", var));
+                        }
+                        if lit != 0 {
+                            model[var - 1] = lit > 0;
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        match status.as_deref() {
+            Some("SATISFIABLE") => Ok(Some(model)),
+            Some("UNSATISFIABLE") => Ok(None),
+            Some(other) => Err(format!("Unknown status {:?}", other)),
+            None => Err("No status line".to_string()),
+        }
+    }
+
+    // Solve with DPLL: unit propagation, then branch on a variable of the
+    // first clause not yet satisfied. Only meant for tiny instances.
+    pub fn dpll(cnf: &Cnf) -> Option<Vec<bool>> {
+        let mut assignment = vec![None; cnf.num_vars];
+        if dpll_rec(&cnf.clauses, &mut assignment) {
+            Some(assignment.iter().map(|v| v.unwrap_or(false)).collect())
+        } else {
+            None
+        }
+    }
+    fn dpll_rec(
+        clauses: &[Vec<Lit>],
+        assignment: &mut Vec<Option<bool>>,
+    ) -> bool {
+        let lit_value = |assignment: &[Option<bool>], lit: Lit| {
+            assignment[lit.unsigned_abs() as usize - 1].map(|v| v == (lit > 0))
+        };
+        // Unit propagation, until nothing changes
+        let mut branch;
+        loop {
+            let mut changed = false;
+            branch = None;
+            for clause in clauses {
+                let mut unassigned = None;
+                let mut num_unassigned = 0;
+                let mut satisfied = false;
+                for &lit in clause {
+                    match lit_value(assignment, lit) {
+                        Some(true) => {
+                            satisfied = true;
+                            break;
+                        }
+                        Some(false) => (),
+                        None => {
+                            unassigned = Some(lit);
+                            num_unassigned += 1;
+                        }
+                    }
+                }
+                if satisfied {
+                    continue;
+                }
+                match (num_unassigned, unassigned) {
+                    (0, _) => return false,
+                    (1, Some(lit)) => {
+                        assignment[lit.unsigned_abs() as usize - 1] =
+                            Some(lit > 0);
+                        changed = true;
+                    }
+                    (_, lit) => branch = branch.or(lit),
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        match branch {
+            None => true,
+            Some(lit) => [lit, -lit].iter().any(|&choice| {
+                let mut attempt = assignment.clone();
+                attempt[choice.unsigned_abs() as usize - 1] = Some(choice > 0);
+                if dpll_rec(clauses, &mut attempt) {
+                    *assignment = attempt;
+                    true
+                } else {
+                    false
+                }
+            }),
         }
-        println!();
     }
-    unreachable!()
 }
 
 /*
     Entrypoint
 */
+// With arguments, run a single command instead:
+//   universal-poset solve N [FILE]     solve for n = N, writing the universal
+//                                      poset found to FILE in DOT format
+// or work with the SAT encoding (see mod sat):
+//   universal-poset dimacs N U         print the formula for n = N, u = U
+//   universal-poset model N U FILE     read a solver's output for it
+//   universal-poset dpll N U           solve it with the built-in solver
+fn run_command(args: &[String]) -> Result<(), String> {
+    use sat::{dpll, parse_model, Encoding};
+    let parse = |arg: Option<&String>, expected: &str| {
+        arg.and_then(|a| a.parse::<usize>().ok())
+            .ok_or(format!("Expected {}", expected))
+    };
+    if args[0] == "solve" {
+        let poset =
+            find_smallest_universal_poset(parse(args.get(1), "a size N")?);
+        if let Some(file) = args.get(2) {
+            fs::write(file, poset.to_dot())
+                .map_err(|e| format!("Could not write {}: {}", file, e))?;
+        }
+        println!("{}", poset.get_size());
+        return Ok(());
+    }
+    let sizes = "sizes N and U";
+    let encoding =
+        Encoding::new(parse(args.get(1), sizes)?, parse(args.get(2), sizes)?);
+    let model = match (args[0].as_str(), args.get(3)) {
+        ("dimacs", None) => {
+            print!("{}", encoding.to_dimacs());
+            return Ok(());
+        }
+        ("model", Some(file)) => {
+            let text = std::fs::read_to_string(file)
+                .map_err(|e| format!("Could not read {}: {}", file, e))?;
+            parse_model(&text, encoding.get_cnf().get_num_vars())?
+        }
+        ("dpll", None) => dpll(encoding.get_cnf()),
+        _ => return Err(format!("Unknown command: {}", args.join(" "))),
+    };
+    match model {
+        None => println!("No universal poset of this size"),
+        Some(model) => {
+            if !encoding.get_cnf().satisfied_by(&model) {
+                return Err("The model does not satisfy the formula".into());
+            }
+            let poset = encoding.decode(&model);
+            if !encoding.verify(&poset) {
+                return Err(format!("Not a universal poset: {:?}", poset));
+            }
+            println!("Universal poset found: {}", poset);
+        }
+    }
+    Ok(())
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(err) = run_command(&args) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
     println!("====== Number of Posets ======");
     for n in 0..7 {
         let posets = enumerate_posets(n);
@@ -757,6 +1590,42 @@ mod tests {
         }
     }
     #[test]
+    fn test_large_poset() {
+        // Larger than the old cap of 20 elements
+        let n = 30;
+        let mut line = Poset::new_unordered(n - 1);
+        line.increase_size_by(1);
+        for i in 1..n {
+            line.add_edge(i - 1, i)
+        }
+        assert_eq!(line.get_num_edges(), n * (n + 1) / 2);
+        assert_eq!(line.get_num_levels(), n);
+    }
+    #[test]
+    fn test_union() {
+        let line = |n: usize| {
+            let mut line = Poset::new_unordered(n);
+            for i in 1..n {
+                line.add_edge(i - 1, i)
+            }
+            line
+        };
+        let mut poset = line(2);
+        poset.union(&line(3));
+        assert_eq!(poset.get_size(), 5);
+        assert_eq!(poset.get_num_edges(), 3 + 6);
+        assert_eq!(poset.get_num_levels(), 3);
+        assert!(poset.contains_edge(0, 1) && poset.contains_edge(2, 4));
+        assert!(!poset.contains_edge(1, 2) && !poset.contains_edge(0, 4));
+        // Same as the union the other way around, up to isomorphism
+        let mut other = line(3);
+        other.union(&line(2));
+        assert_eq!(poset.canonical_form(), other.canonical_form());
+        // The empty poset is a unit
+        poset.union(&Poset::new_unordered(0));
+        assert_eq!(poset.get_num_edges(), 9);
+    }
+    #[test]
     #[should_panic]
     fn test_toosmall() {
         let mut poset = Poset::new_unordered(2);
@@ -773,14 +1642,17 @@ mod tests {
 
     #[test]
     fn test_enumerate_subsets() {
-        assert_eq!(enumerate_subsets(0), vec![vec![],]);
-        assert_eq!(enumerate_subsets(1), vec![vec![], vec![0],]);
+        assert_eq!(enumerate_subsets(0).collect::<Vec<_>>(), vec![vec![],]);
+        assert_eq!(
+            enumerate_subsets(1).collect::<Vec<_>>(),
+            vec![vec![], vec![0],]
+        );
         assert_eq!(
-            enumerate_subsets(2),
+            enumerate_subsets(2).collect::<Vec<_>>(),
             vec![vec![], vec![1], vec![0], vec![0, 1],]
         );
         assert_eq!(
-            enumerate_subsets(3),
+            enumerate_subsets(3).collect::<Vec<_>>(),
             vec![
                 vec![],
                 vec![2],
@@ -796,22 +1668,28 @@ mod tests {
 
     #[test]
     fn test_enumerate_partitions() {
-        assert_eq!(enumerate_partitions(0), vec![vec![],]);
-        assert_eq!(enumerate_partitions(1), vec![vec![1],]);
-        assert_eq!(enumerate_partitions(2), vec![vec![1, 1], vec![2],]);
+        assert_eq!(enumerate_partitions(0).collect::<Vec<_>>(), vec![vec![],]);
+        assert_eq!(enumerate_partitions(1).collect::<Vec<_>>(), vec![vec![1],]);
         assert_eq!(
-            enumerate_partitions(3),
+            enumerate_partitions(2).collect::<Vec<_>>(),
+            vec![vec![1, 1], vec![2],]
+        );
+        assert_eq!(
+            enumerate_partitions(3).collect::<Vec<_>>(),
             vec![vec![1, 1, 1], vec![2, 1], vec![1, 2], vec![3],]
         );
     }
 
     #[test]
     fn test_enumerate_bijections() {
-        assert_eq!(enumerate_bijections(0), vec![vec![],]);
-        assert_eq!(enumerate_bijections(1), vec![vec![0],]);
-        assert_eq!(enumerate_bijections(2), vec![vec![1, 0], vec![0, 1],]);
+        assert_eq!(enumerate_bijections(0).collect::<Vec<_>>(), vec![vec![],]);
+        assert_eq!(enumerate_bijections(1).collect::<Vec<_>>(), vec![vec![0],]);
+        assert_eq!(
+            enumerate_bijections(2).collect::<Vec<_>>(),
+            vec![vec![1, 0], vec![0, 1],]
+        );
         assert_eq!(
-            enumerate_bijections(3),
+            enumerate_bijections(3).collect::<Vec<_>>(),
             vec![
                 vec![2, 1, 0],
                 vec![1, 2, 0],
@@ -825,35 +1703,38 @@ mod tests {
     #[test]
     fn test_enumerate_bijection_lists() {
         assert_eq!(
-            enumerate_bijection_lists(vec![]),
+            enumerate_bijection_lists(vec![]).collect::<Vec<_>>(),
             vec![vec![] as Vec<Vec<usize>>]
         );
-        assert_eq!(enumerate_bijection_lists(vec![0]), vec![vec![vec![],],]);
         assert_eq!(
-            enumerate_bijection_lists(vec![0, 0]),
+            enumerate_bijection_lists(vec![0]).collect::<Vec<_>>(),
+            vec![vec![vec![],],]
+        );
+        assert_eq!(
+            enumerate_bijection_lists(vec![0, 0]).collect::<Vec<_>>(),
             vec![vec![vec![], vec![]],]
         );
         assert_eq!(
-            enumerate_bijection_lists(vec![1, 1]),
+            enumerate_bijection_lists(vec![1, 1]).collect::<Vec<_>>(),
             vec![vec![vec![0], vec![0]],]
         );
         assert_eq!(
-            enumerate_bijection_lists(vec![1, 0]),
+            enumerate_bijection_lists(vec![1, 0]).collect::<Vec<_>>(),
             vec![vec![vec![0], vec![]],]
         );
         assert_eq!(
-            enumerate_bijection_lists(vec![2, 1]),
+            enumerate_bijection_lists(vec![2, 1]).collect::<Vec<_>>(),
             vec![vec![vec![1, 0], vec![0]], vec![vec![0, 1], vec![0]],]
         );
         assert_eq!(
-            enumerate_bijection_lists(vec![1, 2, 0]),
+            enumerate_bijection_lists(vec![1, 2, 0]).collect::<Vec<_>>(),
             vec![
                 vec![vec![0], vec![1, 0], vec![]],
                 vec![vec![0], vec![0, 1], vec![]],
             ]
         );
         assert_eq!(
-            enumerate_bijection_lists(vec![2, 1, 2]),
+            enumerate_bijection_lists(vec![2, 1, 2]).collect::<Vec<_>>(),
             vec![
                 vec![vec![1, 0], vec![0], vec![1, 0]],
                 vec![vec![1, 0], vec![0], vec![0, 1]],
@@ -865,11 +1746,20 @@ mod tests {
 
     #[test]
     fn test_enumerate_injections() {
-        assert_eq!(enumerate_injections(0, 1), vec![vec![],]);
-        assert_eq!(enumerate_injections(1, 2), vec![vec![0], vec![1],]);
-        assert_eq!(enumerate_injections(2, 1), vec![] as Vec<Vec<usize>>);
         assert_eq!(
-            enumerate_injections(2, 3),
+            enumerate_injections(0, 1).collect::<Vec<_>>(),
+            vec![vec![],]
+        );
+        assert_eq!(
+            enumerate_injections(1, 2).collect::<Vec<_>>(),
+            vec![vec![0], vec![1],]
+        );
+        assert_eq!(
+            enumerate_injections(2, 1).collect::<Vec<_>>(),
+            vec![] as Vec<Vec<usize>>
+        );
+        assert_eq!(
+            enumerate_injections(2, 3).collect::<Vec<_>>(),
             vec![
                 vec![1, 0],
                 vec![2, 0],
@@ -910,6 +1800,103 @@ mod tests {
         assert_eq!(enumerate_posets_leveled(&mut vec![3, 2]).len(), 9);
     }
 
+    #[test]
+    fn test_covers() {
+        // Diamond 0 < 1, 2 < 3, plus 4 above 1
+        let edges = [(0, 1), (0, 2), (1, 3), (2, 3), (0, 3), (1, 4)];
+        let poset = Poset::from_dag(5, &edges).unwrap();
+        assert_eq!(poset.get_num_covers(), 5);
+        assert!(poset.is_cover(0, 1) && !poset.is_cover(0, 3));
+        assert!(!poset.is_cover(1, 1) && !poset.is_cover(1, 0));
+        assert_eq!(poset.upper_covers(1).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(poset.lower_covers(3).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(poset.lower_covers(0).count(), 0);
+        let covers: Vec<_> = poset.covers().collect();
+        assert_eq!(covers, vec![(0, 1), (0, 2), (1, 3), (1, 4), (2, 3)]);
+        // The covers generate the same poset
+        let reduced = Poset::from_dag(5, &covers).unwrap();
+        assert_eq!(reduced.get_up_sets(), poset.get_up_sets());
+        // A line has n - 1 covers
+        for n in 1..TEST_UPTO_BIG {
+            let edges: Vec<_> = (1..n).map(|i| (i - 1, i)).collect();
+            let line = Poset::from_dag(n, &edges).unwrap();
+            assert_eq!(line.get_num_covers(), n - 1);
+            assert_eq!(line.get_num_edges(), n * (n + 1) / 2);
+        }
+    }
+    #[test]
+    fn test_from_dag_errors() {
+        use poset::DagError;
+        let cycle = [(0, 1), (1, 2), (2, 0)];
+        assert_eq!(
+            Poset::from_dag(3, &cycle).unwrap_err(),
+            DagError::Cycle(2, 0)
+        );
+        assert_eq!(
+            Poset::from_dag(2, &[(1, 1)]).unwrap_err(),
+            DagError::Cycle(1, 1)
+        );
+        assert_eq!(
+            Poset::from_dag(2, &[(0, 2)]).unwrap_err(),
+            DagError::OutOfRange(0, 2)
+        );
+    }
+
+    #[test]
+    fn test_text_format() {
+        for n in 0..TEST_UPTO_SMALL {
+            for poset in enumerate_posets(n) {
+                let parsed: Poset = poset.to_string().parse().unwrap();
+                assert_eq!(parsed.get_up_sets(), poset.get_up_sets());
+            }
+        }
+        let line: Poset = "3: 0<1 1<2 0<2".parse().unwrap();
+        assert_eq!(line.to_string(), "3: 0<1 1<2");
+        assert_eq!(line.get_num_edges(), 6);
+        assert!("2: 0<1 1<0".parse::<Poset>().is_err());
+        assert!("2: 0<2".parse::<Poset>().is_err());
+        assert!("2 0<1".parse::<Poset>().is_err());
+        assert!("2: 0-1".parse::<Poset>().is_err());
+    }
+    #[test]
+    fn test_dot() {
+        let poset: Poset = "4: 0<1 1<2 0<3".parse().unwrap();
+        let dot = poset.to_dot();
+        assert!(dot.starts_with("digraph poset {"));
+        assert_eq!(dot.matches("rank=same").count(), 3);
+        assert_eq!(dot.matches("->").count(), 3);
+        assert!(dot.contains("1 -> 2;"));
+        assert!(!dot.contains("0 -> 2;"));
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        // Relabelling a poset (here, rotating the labels of a line) does not
+        // change its canonical form
+        for n in 1..TEST_UPTO_BIG {
+            let mut line = Poset::new_unordered(n);
+            let mut rotated = Poset::new_unordered(n);
+            for i in 1..n {
+                line.add_edge(i - 1, i);
+                rotated.add_edge((i + 1) % n, (i + 2) % n);
+            }
+            assert_eq!(line.canonical_form(), rotated.canonical_form());
+        }
+        // Canonical forms agree exactly when posets are isomorphic
+        for n in 0..TEST_UPTO_SMALL {
+            let posets =
+                enumerate_posets_leveled_rec(&mut vec![n - n / 2, n / 2], n);
+            for p1 in &posets {
+                for p2 in &posets {
+                    assert_eq!(
+                        p1.canonical_form() == p2.canonical_form(),
+                        p1.isomorphic(p2)
+                    );
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_enumerate_posets() {
         // https://oeis.org/A000112
@@ -919,8 +1906,65 @@ mod tests {
         assert_eq!(enumerate_posets(3).len(), 5);
         assert_eq!(enumerate_posets(4).len(), 16);
         assert_eq!(enumerate_posets(5).len(), 63);
-        // Disabled slow test
-        // assert_eq!(enumerate_posets(6).len(), 318);
+        assert_eq!(enumerate_posets(6).len(), 318);
+        assert_eq!(enumerate_posets(7).len(), 2045);
+    }
+
+    #[test]
+    fn test_find_embedding() {
+        // Compare against trying every injection
+        let small = enumerate_posets(3);
+        let large = enumerate_posets(4);
+        for p1 in &small {
+            for p2 in &large {
+                let brute_force = enumerate_injections(3, 4).any(|inj| {
+                    (0..3).all(|e| {
+                        (0..3).all(|f| {
+                            p1.contains_edge(e, f)
+                                == p2.contains_edge(inj[e], inj[f])
+                        })
+                    })
+                });
+                match p1.find_embedding(p2) {
+                    Some(emb) => {
+                        assert!(brute_force);
+                        for e in 0..3 {
+                            for f in 0..3 {
+                                assert_eq!(
+                                    p1.contains_edge(e, f),
+                                    p2.contains_edge(emb[e], emb[f])
+                                );
+                            }
+                        }
+                    }
+                    None => assert!(!brute_force),
+                }
+            }
+        }
+        // A large poset embeds in itself
+        let mut line = Poset::new_unordered(TEST_UPTO_BIG);
+        for i in 1..TEST_UPTO_BIG {
+            line.add_edge(i - 1, i);
+        }
+        assert!(line.embeds_in(&line));
+        // Rows of more than one word
+        let line = |n: usize| {
+            let mut line = Poset::new_unordered(n);
+            for i in 1..n {
+                line.add_edge(i - 1, i);
+            }
+            line
+        };
+        let long = line(2 * 64 + 5);
+        assert!(line(100).embeds_in(&long));
+        assert!(long.embeds_in(&long));
+        assert!(!Poset::new_unordered(2).embeds_in(&long));
+        let mut fork = line(100);
+        fork.increase_size_by(1);
+        fork.add_edge(0, 100);
+        assert!(!fork.embeds_in(&long));
+        let emb = line(3).find_embedding(&fork).unwrap();
+        assert!(emb.windows(2).all(|w| fork.contains_edge(w[0], w[1])));
     }
 
     #[test]
@@ -947,6 +1991,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_enumerate_universal_posets_exactly_once() {
+        for n in 1..4 {
+            for size in (n + 1)..7 {
+                let candidates = enumerate_candidate_universal_posets(n, size);
+                let forms: HashSet<_> =
+                    candidates.iter().map(Poset::canonical_form).collect();
+                assert_eq!(forms.len(), candidates.len());
+                let expected = enumerate_posets(size)
+                    .iter()
+                    .filter(|p| p.height() >= n && p.width() >= n)
+                    .count();
+                assert_eq!(candidates.len(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_universal_poset() {
+        // Same result as a sequential search
+        for n in 0..4 {
+            let base_posets = enumerate_posets(n);
+            for size in n..(2 * n + 1) {
+                let candidates = enumerate_candidate_universal_posets(n, size);
+                let expected = candidates.iter().position(|candidate| {
+                    base_posets.iter().all(|base| base.embeds_in(candidate))
+                });
+                assert_eq!(
+                    find_universal_poset(&base_posets, &candidates),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sat_encoding() {
+        use sat::{dpll, parse_model, Encoding};
+        for (n, &expected) in [0, 1, 3, 5].iter().enumerate() {
+            for u in n..(expected + 1) {
+                let encoding = Encoding::new(n, u);
+                match dpll(encoding.get_cnf()) {
+                    Some(model) => {
+                        assert_eq!(u, expected);
+                        assert!(encoding.get_cnf().satisfied_by(&model));
+                        // Read back the model as a solver would print it
+                        let lits: Vec<String> = model
+                            .iter()
+                            .enumerate()
+                            .map(|(v, &b)| {
+                                let v = v as i32 + 1;
+                                (if b { v } else { -v }).to_string()
+                            })
+                            .collect();
+                        let text =
+                            format!("s SATISFIABLE\nv {} 0\n", lits.join(" "));
+                        let num_vars = encoding.get_cnf().get_num_vars();
+                        let parsed = parse_model(&text, num_vars).unwrap();
+                        assert_eq!(parsed, Some(model));
+                        let poset = encoding.decode(&parsed.unwrap());
+                        assert!(encoding.verify(&poset));
+                    }
+                    None => assert!(u < expected),
+                }
+            }
+        }
+        assert_eq!(parse_model("s UNSATISFIABLE\n", 3), Ok(None));
+        assert!(parse_model("v 1 -2 0\n", 3).is_err());
+    }
+
     #[test]
     fn test_solve_universal_poset_easy() {
         assert_eq!(solve_universal_poset(0), 0);
@@ -954,9 +2068,13 @@ mod tests {
         assert_eq!(solve_universal_poset(2), 3);
         assert_eq!(solve_universal_poset(3), 5);
     }
-    // Disabled slow test
-    // #[test]
-    // fn test_solve_universal_poset_hard() {
-    //     assert_eq!(solve_universal_poset(4), 8);
-    // }
+    // Slow test, which also reports the time for solve_universal_poset(4):
+    //     cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn test_solve_universal_poset_hard() {
+        let start = std::time::Instant::now();
+        assert_eq!(solve_universal_poset(4), 8);
+        println!("solve_universal_poset(4): {:.2?}", start.elapsed());
+    }
 }